@@ -0,0 +1,54 @@
+use std::path::Path;
+
+use anyhow::bail;
+
+use crate::utils::{
+    manifest::load_manifest,
+    paths::{normalize_path, to_native_path},
+    project_config::load_project_config,
+    worktree::{TrackingRules, untracked_files},
+};
+
+/// Files `envy add` should encrypt: untracked files the tracking rules want,
+/// under each directory in `paths` (the whole project when empty). Files named
+/// explicitly are added unless the rules exclude them.
+pub fn add_targets(paths: &[String]) -> anyhow::Result<Vec<String>> {
+    let project = load_project_config()?;
+    let rules = TrackingRules::load(&project)?;
+    let manifest = load_manifest()?;
+
+    if paths.is_empty() {
+        return Ok(untracked_files(Path::new("."), &manifest, &rules));
+    }
+
+    let mut targets = Vec::new();
+
+    for path in paths {
+        let normalized = normalize_path(path);
+        let native = to_native_path(&normalized);
+
+        if native.is_dir() || normalized.is_empty() {
+            let root = if normalized.is_empty() {
+                Path::new(".").to_path_buf()
+            } else {
+                native
+            };
+            targets.extend(untracked_files(&root, &manifest, &rules));
+        } else if native.is_file() {
+            if rules.excludes(&normalized) {
+                bail!(
+                    "'{}' is excluded by the tracking rules. Use `envy encrypt -i {}` to track it anyway.",
+                    normalized,
+                    normalized
+                );
+            }
+            targets.push(normalized);
+        } else {
+            bail!("'{}' does not exist", path);
+        }
+    }
+
+    targets.sort();
+    targets.dedup();
+    Ok(targets)
+}
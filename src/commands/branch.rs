@@ -0,0 +1,165 @@
+use anyhow::bail;
+use console::style;
+
+use crate::{
+    commands::history::move_worktree,
+    utils::{
+        commit::{
+            current_branch, delete_branch, delete_remote_ref, is_known_ancestor, list_branches,
+            read_branch, read_detached, read_head, read_remote_ref, resolve_commit,
+            set_head_branch, validate_branch_name, write_branch,
+        },
+        ui::{print_info, print_kv, print_success, short_hash},
+    },
+};
+
+pub fn list() -> anyhow::Result<()> {
+    let branches = list_branches();
+    let current = current_branch();
+
+    if let Some(head) = read_detached().and_then(|_| read_head()) {
+        println!(
+            "{} {}",
+            style("*").green(),
+            style(format!("(detached at {})", short_hash(&head, 8))).yellow()
+        );
+    }
+
+    if branches.is_empty() {
+        print_info(&format!(
+            "No branches yet. The first commit creates '{}'.",
+            current.unwrap_or_default()
+        ));
+        return Ok(());
+    }
+
+    for (name, hash) in &branches {
+        let remote = match read_remote_ref(name) {
+            Some(remote) if remote == *hash => String::new(),
+            Some(_) => style(" (differs from remote)").yellow().to_string(),
+            None => style(" (not pushed)").dim().to_string(),
+        };
+
+        if current.as_ref() == Some(name) {
+            println!(
+                "{} {} {}{}",
+                style("*").green(),
+                style(name).green().bold(),
+                style(short_hash(hash, 8)).dim(),
+                remote
+            );
+        } else {
+            println!("  {} {}{}", name, style(short_hash(hash, 8)).dim(), remote);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn create(name: &str, start: Option<&str>) -> anyhow::Result<()> {
+    validate_branch_name(name)?;
+    if read_branch(name).is_some() {
+        bail!("Branch '{}' already exists.", name);
+    }
+
+    let target = resolve_commit(start.unwrap_or("HEAD"))?;
+    write_branch(name, &target)?;
+
+    print_success(&format!(
+        "Created branch '{}' at {}.",
+        name,
+        short_hash(&target, 8)
+    ));
+    print_info(&format!(
+        "Run {} to work on it.",
+        style(format!("`envy switch {}`", name)).cyan()
+    ));
+
+    Ok(())
+}
+
+/// Deletes a local branch. Without `force`, a branch is only deleted if its
+/// commits are on the remote or in the history of HEAD.
+pub fn delete(name: &str, force: bool) -> anyhow::Result<()> {
+    validate_branch_name(name)?;
+    let Some(hash) = read_branch(name) else {
+        bail!("Branch '{}' not found.", name);
+    };
+
+    if current_branch().as_deref() == Some(name) || read_detached().as_deref() == Some(name) {
+        bail!("Cannot delete the branch you are on. Switch to another branch first.");
+    }
+
+    let remote = read_remote_ref(name);
+    let merged = remote.as_ref() == Some(&hash)
+        || read_head().is_some_and(|head| is_known_ancestor(&hash, &head));
+    if !force && !merged {
+        bail!(
+            "Branch '{}' has commits that are neither pushed nor in the current branch. Re-run with `--force` to delete it anyway.",
+            name
+        );
+    }
+
+    delete_branch(name)?;
+    delete_remote_ref(name)?;
+
+    print_success(&format!(
+        "Deleted branch '{}' (was {}).",
+        name,
+        short_hash(&hash, 8)
+    ));
+    if remote.is_some() {
+        print_info("The branch still exists on the remote.");
+    }
+
+    Ok(())
+}
+
+/// Points HEAD at a branch and writes its files to the working tree. With
+/// `create`, a new branch is started at HEAD and nothing on disk changes.
+pub async fn switch(name: &str, create: bool) -> anyhow::Result<()> {
+    validate_branch_name(name)?;
+
+    if create {
+        if read_branch(name).is_some() {
+            bail!("Branch '{}' already exists.", name);
+        }
+        if let Some(head) = read_head() {
+            write_branch(name, &head)?;
+        }
+        set_head_branch(name)?;
+        print_success(&format!("Switched to a new branch '{}'.", name));
+        return Ok(());
+    }
+
+    let Some(target) = read_branch(name) else {
+        bail!(
+            "Branch '{0}' not found. Create it with `envy switch -c {0}`, then run `envy pull` if it exists on the remote.",
+            name
+        );
+    };
+
+    if current_branch().as_deref() == Some(name) {
+        print_info(&format!("Already on '{}'.", name));
+        return Ok(());
+    }
+
+    move_worktree(&target, "switch branches").await?;
+    set_head_branch(name)?;
+
+    print_kv("HEAD", short_hash(&target, 12));
+    print_success(&format!("Switched to branch '{}'.", name));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delete_rejects_invalid_names() {
+        assert!(delete("../x", true).is_err());
+        assert!(delete("../../config.toml", true).is_err());
+    }
+}
@@ -0,0 +1,49 @@
+use std::io::Write;
+use std::path::Path;
+
+use zeroize::Zeroize;
+
+use crate::{
+    commands::crypto::decrypt_tracked_file,
+    utils::{
+        commit::load_commit_manifest,
+        manifest::load_manifest,
+        paths::{ensure_parent_exists, normalize_path},
+        ui::print_success,
+    },
+};
+
+pub fn cat(path: &str, commit: Option<&str>, output: Option<&str>) -> anyhow::Result<()> {
+    let manifest = match commit {
+        Some(rev) => load_commit_manifest(rev)?,
+        None => load_manifest()?,
+    };
+
+    let normalized = normalize_path(path);
+    let mut plaintext = decrypt_tracked_file(&manifest, &normalized)?;
+
+    let result = match output {
+        Some(target) => {
+            let target_path = Path::new(target);
+            ensure_parent_exists(target_path)
+                .and_then(|_| std::fs::write(target_path, &plaintext))
+                .map_err(|e| anyhow::anyhow!("Failed to write '{}': {}", target, e))
+        }
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            stdout
+                .write_all(&plaintext)
+                .and_then(|_| stdout.flush())
+                .map_err(|e| anyhow::anyhow!("Failed to write to stdout: {}", e))
+        }
+    };
+
+    plaintext.zeroize();
+    result?;
+
+    if let Some(target) = output {
+        print_success(&format!("Decrypted '{}' to '{}'.", normalized, target));
+    }
+
+    Ok(())
+}
@@ -1,15 +1,23 @@
 use console::style;
+use std::collections::HashMap;
 use std::time::{Duration, UNIX_EPOCH};
 
+use crate::commands::{rekey::ensure_no_pending_rekey, validate::verify_before};
 use crate::utils::{
-    commit::{Commit, get_head_manifest_hash, read_head, save_commit, walk_history, write_head},
+    commit::{
+        Commit, ensure_attached, get_head_manifest_hash, read_head, save_commit, walk_history,
+        write_head,
+    },
     manifest::{
         compute_manifest_content_hash, load_manifest, load_manifest_by_hash, save_manifest,
     },
-    ui::{print_header, print_info, print_kv, print_success},
+    tag::{list_tags, try_load_tag},
+    ui::{print_header, print_info, print_kv, print_success, short_hash},
 };
 
-pub fn commit(message: &str, author: Option<String>) -> anyhow::Result<()> {
+pub fn commit(message: &str, author: Option<String>, no_verify: bool) -> anyhow::Result<()> {
+    ensure_attached("commit")?;
+    ensure_no_pending_rekey("commit")?;
     let manifest = load_manifest()?;
 
     let current_content_hash = compute_manifest_content_hash(&manifest);
@@ -27,6 +35,10 @@ pub fn commit(message: &str, author: Option<String>) -> anyhow::Result<()> {
         }
     }
 
+    if !no_verify {
+        verify_before("commit", &manifest)?;
+    }
+
     let manifest_hash = save_manifest(&manifest)?;
 
     let parent = read_head();
@@ -38,8 +50,8 @@ pub fn commit(message: &str, author: Option<String>) -> anyhow::Result<()> {
     write_head(&commit_hash)?;
 
     print_header("Commit created");
-    print_kv("Commit", &commit_hash[..12]);
-    print_kv("Manifest", &manifest_hash[..12]);
+    print_kv("Commit", short_hash(&commit_hash, 12));
+    print_kv("Manifest", short_hash(&manifest_hash, 12));
     print_kv("Files", &manifest.files.len().to_string());
     print_success(&format!("\"{}\"", message));
 
@@ -61,19 +73,31 @@ pub fn log(max_count: usize) -> anyhow::Result<()> {
         return Ok(());
     }
 
+    let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+    for (name, tag_hash) in list_tags() {
+        if let Some(tag) = try_load_tag(&tag_hash) {
+            tags.entry(tag.commit).or_default().push(name);
+        }
+    }
+
     println!();
     for (hash, commit) in commits {
-        print_commit_entry(&hash, &commit);
+        print_commit_entry(&hash, &commit, tags.get(&hash));
     }
 
     Ok(())
 }
 
-fn print_commit_entry(hash: &str, commit: &Commit) {
+fn print_commit_entry(hash: &str, commit: &Commit, tags: Option<&Vec<String>>) {
+    let decoration = match tags {
+        Some(names) => format!(" (tag: {})", names.join(", tag: ")),
+        None => String::new(),
+    };
     println!(
-        "{} {}",
+        "{} {}{}",
         style("commit").yellow().bold(),
-        style(&hash[..12]).yellow()
+        style(short_hash(hash, 12)).yellow(),
+        style(decoration).cyan()
     );
 
     if let Some(ref author) = commit.author {
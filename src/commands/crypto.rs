@@ -1,13 +1,28 @@
-const BLOB_VERSION: u8 = 1;
+const LEGACY_BLOB_VERSION: u8 = 1;
+const PARAMS_BLOB_VERSION: u8 = 3;
+const BLOB_VERSION: u8 = 4;
 
 const VERSION_LEN: usize = 1;
 const SALT_LEN: usize = 16;
 const NONCE_LEN: usize = 24;
 
-const HEADER_LEN: usize = VERSION_LEN + SALT_LEN + NONCE_LEN;
+const LEGACY_HEADER_LEN: usize = VERSION_LEN + SALT_LEN + NONCE_LEN;
+
+const KDF_ARGON2ID: u8 = 1;
+const KDF_PARAMS_LEN: usize = 1 + 4 + 4 + 4;
+
+const HEADER_LEN: usize = VERSION_LEN + KDF_PARAMS_LEN + SALT_LEN + NONCE_LEN;
+
+// Upper bounds for key derivation parameters. Blob headers are untrusted, so a
+// tampered blob cannot make the client allocate unbounded memory or spin
+// forever, and the same bounds keep envy from writing blobs it refuses to open.
+const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_ITERATIONS: u32 = 64;
+const MAX_PARALLELISM: u32 = 16;
 
 const KEY_LEN: usize = 32;
-const KEY_BLOB_VERSION: u8 = 2;
+const LEGACY_KEY_BLOB_VERSION: u8 = 2;
+const KEY_BLOB_VERSION: u8 = 5;
 
 const KEY_VERSION_LEN: usize = 1;
 const KEY_NONCE_LEN: usize = 24;
@@ -18,72 +33,240 @@ use argon2::{
     Algorithm, Argon2, Params, Version,
     password_hash::rand_core::{OsRng, RngCore},
 };
-use chacha20poly1305::{KeyInit, XChaCha20Poly1305, XNonce, aead::Aead};
+use chacha20poly1305::{
+    KeyInit, XChaCha20Poly1305, XNonce,
+    aead::{Aead, Payload},
+};
+use hmac::{Hmac, Mac};
 use sha2::{Digest, Sha256};
 use std::fs;
+use std::path::Path;
 use zeroize::Zeroize;
 
-use crate::utils::manifest::{load_manifest, save_manifest};
-use crate::utils::paths::normalize_path;
+use crate::utils::manifest::{
+    FileEntry, Manifest, Scheme, get_project_key, load_manifest, save_manifest,
+};
+use crate::utils::paths::{file_mode, normalize_path};
+use crate::utils::project_config::load_project_config;
+use crate::utils::storage::verify_object_hash;
+use crate::utils::ui::{prompt_passphrase, short_hash};
+
+/// Object types bound into the associated data of every blob, so a blob cannot
+/// be replayed as a different kind of object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    File,
+    DataKey,
+    Manifest,
+    Commit,
+    Tag,
+}
+
+impl ObjectKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ObjectKind::File => "file",
+            ObjectKind::DataKey => "data-key",
+            ObjectKind::Manifest => "manifest",
+            ObjectKind::Commit => "commit",
+            ObjectKind::Tag => "tag",
+        }
+    }
+}
+
+/// Associated data authenticating the project, object type and, for file blobs
+/// and their data keys, the normalized tracked path.
+pub fn object_aad(project_id: &str, kind: ObjectKind, path: &str) -> Vec<u8> {
+    format!("envoy:{}:{}:{}", project_id, kind.as_str(), path).into_bytes()
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Keyed hash of a file's plaintext, recorded in the manifest so changes can be
+/// detected without decrypting. The HMAC key is derived from the manifest key,
+/// so the digest reveals nothing to anyone without the project passphrase.
+pub fn plaintext_digest(manifest_key: &[u8], plaintext: &[u8]) -> Result<String> {
+    let mut digest_key: [u8; KEY_LEN] = <HmacSha256 as Mac>::new_from_slice(manifest_key)
+        .map_err(|_| anyhow::anyhow!("Invalid manifest key"))?
+        .chain_update(b"envoy:plaintext-digest")
+        .finalize()
+        .into_bytes()
+        .into();
+
+    let mac = <HmacSha256 as Mac>::new_from_slice(&digest_key)
+        .map_err(|_| anyhow::anyhow!("Invalid digest key"))?
+        .chain_update(plaintext);
+    digest_key.zeroize();
+
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self::LEGACY
+    }
+}
+
+impl KdfParams {
+    /// Parameters used by version 1 blobs and for the manifest key.
+    pub const LEGACY: KdfParams = KdfParams {
+        memory_kib: 19456,
+        iterations: 2,
+        parallelism: 1,
+    };
+
+    /// Checks the parameters against the bounds enforced when decrypting.
+    pub fn validate(&self) -> Result<()> {
+        if self.memory_kib > MAX_MEMORY_KIB
+            || self.iterations > MAX_ITERATIONS
+            || self.parallelism > MAX_PARALLELISM
+        {
+            bail!(
+                "Key derivation parameters are out of range (memory_kib <= {}, iterations <= {}, parallelism <= {})",
+                MAX_MEMORY_KIB,
+                MAX_ITERATIONS,
+                MAX_PARALLELISM
+            );
+        }
+        self.argon2()?;
+
+        Ok(())
+    }
+
+    pub fn argon2(&self) -> Result<Argon2<'static>> {
+        let params = Params::new(
+            self.memory_kib,
+            self.iterations,
+            self.parallelism,
+            Some(KEY_LEN),
+        )
+        .map_err(|e| anyhow::anyhow!("Invalid Argon2 parameters: {}", e))?;
+
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
 
-pub fn encrypt_bytes(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    fn to_header(self) -> [u8; KDF_PARAMS_LEN] {
+        let mut header = [0u8; KDF_PARAMS_LEN];
+        header[0] = KDF_ARGON2ID;
+        header[1..5].copy_from_slice(&self.memory_kib.to_le_bytes());
+        header[5..9].copy_from_slice(&self.iterations.to_le_bytes());
+        header[9..13].copy_from_slice(&self.parallelism.to_le_bytes());
+        header
+    }
+
+    fn from_header(header: &[u8]) -> Result<Self> {
+        if header[0] != KDF_ARGON2ID {
+            bail!(
+                "Unsupported key derivation algorithm ({}). Please update envy.",
+                header[0]
+            );
+        }
+
+        let read_u32 =
+            |start: usize| u32::from_le_bytes(header[start..start + 4].try_into().unwrap());
+
+        let params = Self {
+            memory_kib: read_u32(1),
+            iterations: read_u32(5),
+            parallelism: read_u32(9),
+        };
+
+        params.validate().map_err(|_| {
+            anyhow::anyhow!("Invalid encrypted data: key derivation parameters are out of range")
+        })?;
+
+        Ok(params)
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], params: &KdfParams) -> Result<[u8; KEY_LEN]> {
     let mut pass = passphrase.as_bytes().to_vec();
+    let mut key = [0u8; KEY_LEN];
 
-    let mut salt = [0u8; 16];
-    OsRng.fill_bytes(&mut salt);
+    let result = params
+        .argon2()?
+        .hash_password_into(&pass, salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("Failed to derive encryption key: {}", e));
+    pass.zeroize();
+    result?;
 
-    let argon2 = Argon2::new(
-        Algorithm::Argon2id,
-        Version::V0x13,
-        Params::new(19456, 2, 1, Some(32))
-            .map_err(|e| anyhow::anyhow!("Failed to create Argon2 params: {}", e))?,
-    );
+    Ok(key)
+}
 
-    let mut key = [0u8; 32];
-    argon2
-        .hash_password_into(&pass, &salt, &mut key)
-        .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))?;
+pub fn encrypt_bytes(
+    plaintext: &[u8],
+    passphrase: &str,
+    params: &KdfParams,
+    aad: &[u8],
+) -> Result<Vec<u8>> {
+    params.validate()?;
+
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+
+    let mut key = derive_key(passphrase, &salt, params)?;
 
     let cipher = XChaCha20Poly1305::new(&key.into());
 
-    let mut nonce_bytes = [0u8; 24];
+    let mut nonce_bytes = [0u8; NONCE_LEN];
     OsRng.fill_bytes(&mut nonce_bytes);
     let nonce = XNonce::from_slice(&nonce_bytes);
 
     let ciphertext = cipher
-        .encrypt(nonce, plaintext)
-        .map_err(|e| anyhow::anyhow!("Encryption failed {}", e))?;
+        .encrypt(
+            nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|e| anyhow::anyhow!("Encryption failed {}", e));
+    key.zeroize();
+    let ciphertext = ciphertext?;
 
     let mut output = Vec::with_capacity(HEADER_LEN + ciphertext.len());
 
     output.push(BLOB_VERSION);
+    output.extend_from_slice(&params.to_header());
     output.extend_from_slice(&salt);
     output.extend_from_slice(&nonce_bytes);
     output.extend_from_slice(&ciphertext);
 
-    pass.zeroize();
-    key.zeroize();
-
     Ok(output)
 }
 
-pub fn decrypt_bytes(encrypted_data: &[u8], passphrase: &str) -> Result<Vec<u8>> {
-    let mut pass = passphrase.as_bytes().to_vec();
-
-    if encrypted_data.len() < HEADER_LEN {
+pub fn decrypt_bytes(encrypted_data: &[u8], passphrase: &str, aad: &[u8]) -> Result<Vec<u8>> {
+    if encrypted_data.is_empty() {
         bail!("Invalid encrypted data: file is too short or corrupted");
     }
 
     let version = encrypted_data[0];
 
-    if version != BLOB_VERSION {
-        bail!(
+    let (params, salt_start) = match version {
+        LEGACY_BLOB_VERSION if encrypted_data.len() >= LEGACY_HEADER_LEN => {
+            (KdfParams::LEGACY, VERSION_LEN)
+        }
+        PARAMS_BLOB_VERSION | BLOB_VERSION if encrypted_data.len() >= HEADER_LEN => {
+            let params =
+                KdfParams::from_header(&encrypted_data[VERSION_LEN..VERSION_LEN + KDF_PARAMS_LEN])?;
+            (params, VERSION_LEN + KDF_PARAMS_LEN)
+        }
+        LEGACY_BLOB_VERSION | PARAMS_BLOB_VERSION | BLOB_VERSION => {
+            bail!("Invalid encrypted data: file is too short or corrupted")
+        }
+        _ => bail!(
             "Unsupported encryption format (version {}). Please update envy.",
             version
-        );
-    }
+        ),
+    };
 
-    let salt_start = VERSION_LEN;
     let nonce_start = salt_start + SALT_LEN;
     let ciphertext_start = nonce_start + NONCE_LEN;
 
@@ -91,82 +274,293 @@ pub fn decrypt_bytes(encrypted_data: &[u8], passphrase: &str) -> Result<Vec<u8>>
     let nonce_bytes = &encrypted_data[nonce_start..ciphertext_start];
     let ciphertext = &encrypted_data[ciphertext_start..];
 
-    let argon2 = Argon2::new(
-        Algorithm::Argon2id,
-        Version::V0x13,
-        Params::new(19456, 2, 1, Some(32))
-            .map_err(|e| anyhow::anyhow!("Failed to initialize encryption: {}", e))?,
-    );
-
-    let mut key = [0u8; 32];
-    argon2
-        .hash_password_into(&pass, salt, &mut key)
-        .map_err(|e| anyhow::anyhow!("Failed to derive encryption key: {}", e))?;
+    let mut key = derive_key(passphrase, salt, &params)?;
 
     let cipher = XChaCha20Poly1305::new(&key.into());
     let nonce = XNonce::from_slice(nonce_bytes);
 
-    let plaintext = cipher
-        .decrypt(nonce, ciphertext)
-        .map_err(|_| anyhow::anyhow!("Decryption failed: wrong passphrase or corrupted data"))?;
+    // Blobs written before associated data was introduced authenticate nothing beyond the ciphertext.
+    let aad = if version == BLOB_VERSION { aad } else { &[] };
 
-    pass.zeroize();
+    let plaintext = cipher
+        .decrypt(
+            nonce,
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| anyhow::anyhow!("Decryption failed: wrong passphrase or corrupted data"));
     key.zeroize();
 
-    Ok(plaintext)
+    plaintext
 }
 
-pub fn encrypt_file(path: &str, passphrase: &str) -> Result<()> {
-    let mut manifest = load_manifest()?;
+fn store_blob(encrypted: &[u8]) -> Result<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(encrypted);
+    let hash_hex = hex::encode(hasher.finalize());
 
-    let normalized_path = normalize_path(path);
+    let filename = format!(".envoy/cache/{}.blob", hash_hex);
+    fs::write(&filename, encrypted)
+        .map_err(|e| anyhow::anyhow!("Failed to write encrypted blob: {}", e))?;
 
-    let plaintext =
-        fs::read(path).map_err(|e| anyhow::anyhow!("Failed to read file '{}': {}", path, e))?;
+    Ok(hash_hex)
+}
 
-    let output = encrypt_bytes(&plaintext, passphrase)?;
+/// The key a tracked file is encrypted with.
+pub enum FileKey {
+    Passphrase(String),
+    /// A fresh random data key wrapped with the manifest key.
+    Project,
+}
 
-    let mut hasher = Sha256::new();
-    hasher.update(&output);
-    let hash = hasher.finalize();
+impl Drop for FileKey {
+    fn drop(&mut self) {
+        if let FileKey::Passphrase(passphrase) = self {
+            passphrase.zeroize();
+        }
+    }
+}
 
-    let hash_hex = hex::encode(hash);
+/// Encrypts `plaintext` as the contents of the tracked (normalized) `path` and
+/// records the new entry in `manifest`. The caller saves the manifest.
+pub fn store_tracked_file(
+    manifest: &mut Manifest,
+    path: &str,
+    plaintext: &[u8],
+    mode: Option<u32>,
+    key: &FileKey,
+) -> Result<()> {
+    let project = load_project_config()?;
+    let manifest_key = get_project_key()?;
+    let aad = object_aad(&project.project_id, ObjectKind::File, path);
+
+    let (blob, wrapped_key) = match key {
+        FileKey::Passphrase(passphrase) => {
+            let params = project.crypto.kdf_params()?;
+            let output = encrypt_bytes(plaintext, passphrase, &params, &aad)?;
+            (store_blob(&output)?, None)
+        }
+        FileKey::Project => {
+            let mut data_key = [0u8; KEY_LEN];
+            OsRng.fill_bytes(&mut data_key);
+
+            let output = encrypt_bytes_with_key(plaintext, &data_key, &aad);
+            let wrapped_key = encrypt_bytes_with_key(
+                &data_key,
+                &manifest_key,
+                &object_aad(&project.project_id, ObjectKind::DataKey, path),
+            );
+            data_key.zeroize();
+
+            (store_blob(&output?)?, Some(hex::encode(wrapped_key?)))
+        }
+    };
+
+    let scheme = match key {
+        FileKey::Passphrase(_) => Scheme::Passphrase,
+        FileKey::Project => Scheme::ProjectKey,
+    };
+
+    manifest.files.insert(
+        path.to_string(),
+        FileEntry {
+            blob,
+            scheme,
+            key: wrapped_key,
+            digest: Some(plaintext_digest(&manifest_key, plaintext)?),
+            mode,
+            size: Some(plaintext.len() as u64),
+        },
+    );
 
-    let filename = format!(".envoy/cache/{}.blob", hash_hex);
-    fs::write(&filename, &output)
-        .map_err(|e| anyhow::anyhow!("Failed to write encrypted blob: {}", e))?;
+    Ok(())
+}
+
+/// Encrypts each file with its key and records them all with a single manifest save.
+pub fn encrypt_files(files: &[(String, FileKey)]) -> Result<()> {
+    let mut manifest = load_manifest()?;
+
+    for (path, key) in files {
+        let mut plaintext =
+            fs::read(path).map_err(|e| anyhow::anyhow!("Failed to read file '{}': {}", path, e))?;
+
+        let result = store_tracked_file(
+            &mut manifest,
+            &normalize_path(path),
+            &plaintext,
+            file_mode(Path::new(path)),
+            key,
+        );
+        plaintext.zeroize();
+        result?;
+    }
 
-    manifest.files.insert(normalized_path, hash_hex);
     save_manifest(&manifest)?;
 
     Ok(())
 }
 
-pub fn decrypt_files(passphrase: &str) -> Result<()> {
-    let manifest = load_manifest()?;
-    for (filename, blob_hash) in manifest.files {
-        let path = format!(".envoy/cache/{}.blob", blob_hash);
-        let encrypted = fs::read(&path)?;
+pub fn unwrap_data_key(
+    wrapped_key: &str,
+    manifest_key: &[u8],
+    project_id: &str,
+    path: &str,
+) -> Result<Vec<u8>> {
+    let wrapped =
+        hex::decode(wrapped_key).map_err(|_| anyhow::anyhow!("Invalid wrapped data key"))?;
+
+    decrypt_bytes_with_key(
+        &wrapped,
+        manifest_key,
+        &object_aad(project_id, ObjectKind::DataKey, path),
+    )
+    .map_err(|_| anyhow::anyhow!("Failed to unwrap data key"))
+}
 
-        let expected_hash = blob_hash;
+/// A project-key file re-encrypted under a fresh data key.
+pub struct RotatedFile {
+    pub blob: String,
+    pub wrapped_key: String,
+    pub digest: String,
+}
 
-        let mut hasher = Sha256::new();
-        hasher.update(&encrypted);
+/// Re-encrypts a project-key blob under a fresh data key wrapped with
+/// `new_key`, so whoever holds the old project key cannot unwrap it.
+pub fn rotate_data_key(
+    blob: &str,
+    wrapped_key: &str,
+    old_key: &[u8],
+    new_key: &[u8],
+    project_id: &str,
+    path: &str,
+) -> Result<RotatedFile> {
+    let mut plaintext =
+        decrypt_with_wrapped_key(&read_blob(blob)?, wrapped_key, old_key, project_id, path)?;
+
+    let mut data_key = [0u8; KEY_LEN];
+    OsRng.fill_bytes(&mut data_key);
+
+    let output = encrypt_bytes_with_key(
+        &plaintext,
+        &data_key,
+        &object_aad(project_id, ObjectKind::File, path),
+    );
+    let wrapped = encrypt_bytes_with_key(
+        &data_key,
+        new_key,
+        &object_aad(project_id, ObjectKind::DataKey, path),
+    );
+    let digest = plaintext_digest(new_key, &plaintext);
+    data_key.zeroize();
+    plaintext.zeroize();
+
+    Ok(RotatedFile {
+        blob: store_blob(&output?)?,
+        wrapped_key: hex::encode(wrapped?),
+        digest: digest?,
+    })
+}
 
-        let computed_hash = hex::encode(hasher.finalize());
+pub fn decrypt_with_wrapped_key(
+    encrypted: &[u8],
+    wrapped_key: &str,
+    manifest_key: &[u8],
+    project_id: &str,
+    path: &str,
+) -> Result<Vec<u8>> {
+    let mut data_key = unwrap_data_key(wrapped_key, manifest_key, project_id, path)?;
+
+    let plaintext = decrypt_bytes_with_key(
+        encrypted,
+        &data_key,
+        &object_aad(project_id, ObjectKind::File, path),
+    );
+    data_key.zeroize();
 
-        if computed_hash != expected_hash {
-            bail!("Encrypted blob integrity check failed (hash mismatch)");
-        }
+    plaintext
+}
 
-        let plaintext = decrypt_bytes(&encrypted, passphrase)?;
+pub fn read_blob(hash: &str) -> Result<Vec<u8>> {
+    let path = format!(".envoy/cache/{}.blob", hash);
 
-        fs::write(&filename, plaintext)?;
+    if !Path::new(&path).exists() {
+        bail!(
+            "Blob {} not found in cache. Run `envy pull` to fetch it.",
+            short_hash(hash, 12)
+        );
     }
-    Ok(())
+
+    let encrypted = fs::read(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read blob {}: {}", short_hash(hash, 12), e))?;
+
+    verify_object_hash(&encrypted, hash, "Cached blob").map_err(|e| {
+        anyhow::anyhow!(
+            "{}. Delete it and run `envy pull` to fetch a fresh copy.",
+            e
+        )
+    })?;
+
+    Ok(encrypted)
+}
+
+pub fn decrypt_tracked_file(manifest: &Manifest, path: &str) -> Result<Vec<u8>> {
+    unlock_tracked_file(manifest, path).map(|(plaintext, _)| plaintext)
+}
+
+/// Decrypts a tracked file and returns the key it was encrypted with, so that
+/// edited contents can be re-encrypted the same way.
+pub fn unlock_tracked_file(manifest: &Manifest, path: &str) -> Result<(Vec<u8>, FileKey)> {
+    unlock_tracked_file_with(manifest, path, None)
 }
 
-pub fn decrypt_bytes_with_key(encrypted_data: &[u8], manifest_key: &[u8]) -> Result<Vec<u8>> {
+/// Like `unlock_tracked_file`, using `passphrase` for passphrase-mode files
+/// instead of prompting when one is given.
+pub fn unlock_tracked_file_with(
+    manifest: &Manifest,
+    path: &str,
+    passphrase: Option<String>,
+) -> Result<(Vec<u8>, FileKey)> {
+    let entry = manifest
+        .files
+        .get(path)
+        .ok_or_else(|| anyhow::anyhow!("File '{}' is not tracked.", path))?;
+
+    let encrypted = read_blob(&entry.blob)?;
+    let project = load_project_config()?;
+
+    if let Some(wrapped_key) = &entry.key {
+        let manifest_key = get_project_key()?;
+        let plaintext = decrypt_with_wrapped_key(
+            &encrypted,
+            wrapped_key,
+            &manifest_key,
+            &project.project_id,
+            path,
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to decrypt '{}': {}", path, e))?;
+
+        return Ok((plaintext, FileKey::Project));
+    }
+
+    let passphrase = match passphrase {
+        Some(passphrase) => passphrase,
+        None => prompt_passphrase(&format!("Passphrase for {}", path), 6)?,
+    };
+    let aad = object_aad(&project.project_id, ObjectKind::File, path);
+
+    let plaintext = decrypt_bytes(&encrypted, &passphrase, &aad)
+        .map_err(|_| anyhow::anyhow!("Wrong passphrase for '{}'", path))?;
+
+    Ok((plaintext, FileKey::Passphrase(passphrase)))
+}
+
+pub fn decrypt_bytes_with_key(
+    encrypted_data: &[u8],
+    manifest_key: &[u8],
+    aad: &[u8],
+) -> Result<Vec<u8>> {
     if manifest_key.len() != KEY_LEN {
         bail!("Invalid encryption key length");
     }
@@ -176,7 +570,7 @@ pub fn decrypt_bytes_with_key(encrypted_data: &[u8], manifest_key: &[u8]) -> Res
     }
 
     let version = encrypted_data[0];
-    if version != KEY_BLOB_VERSION {
+    if version != KEY_BLOB_VERSION && version != LEGACY_KEY_BLOB_VERSION {
         bail!(
             "Unsupported encryption format (version {}). Please update envy.",
             version
@@ -192,14 +586,30 @@ pub fn decrypt_bytes_with_key(encrypted_data: &[u8], manifest_key: &[u8]) -> Res
     let cipher = XChaCha20Poly1305::new(manifest_key.into());
     let nonce = XNonce::from_slice(nonce_bytes);
 
+    let aad = if version == KEY_BLOB_VERSION {
+        aad
+    } else {
+        &[]
+    };
+
     let plaintext = cipher
-        .decrypt(nonce, ciphertext)
+        .decrypt(
+            nonce,
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
         .map_err(|_| anyhow::anyhow!("Decryption failed: wrong passphrase or corrupted data"))?;
 
     Ok(plaintext)
 }
 
-pub fn encrypt_bytes_with_key(plaintext: &[u8], manifest_key: &[u8]) -> Result<Vec<u8>> {
+pub fn encrypt_bytes_with_key(
+    plaintext: &[u8],
+    manifest_key: &[u8],
+    aad: &[u8],
+) -> Result<Vec<u8>> {
     if manifest_key.len() != KEY_LEN {
         bail!("Invalid manifest key length");
     }
@@ -211,7 +621,13 @@ pub fn encrypt_bytes_with_key(plaintext: &[u8], manifest_key: &[u8]) -> Result<V
     let nonce = XNonce::from_slice(&nonce_bytes);
 
     let ciphertext = cipher
-        .encrypt(nonce, plaintext)
+        .encrypt(
+            nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
         .map_err(|e| anyhow::anyhow!("Encryption failed: {}", e))?;
 
     let mut output = Vec::with_capacity(KEY_HEADER_LEN + ciphertext.len());
@@ -222,3 +638,88 @@ pub fn encrypt_bytes_with_key(plaintext: &[u8], manifest_key: &[u8]) -> Result<V
 
     Ok(output)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_PARAMS: KdfParams = KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    #[test]
+    fn test_blob_records_kdf_params() {
+        let encrypted = encrypt_bytes(b"FOO=bar", "passphrase", &TEST_PARAMS, b"").unwrap();
+
+        assert_eq!(encrypted[0], BLOB_VERSION);
+        assert_eq!(
+            KdfParams::from_header(&encrypted[VERSION_LEN..VERSION_LEN + KDF_PARAMS_LEN]).unwrap(),
+            TEST_PARAMS
+        );
+        assert_eq!(
+            decrypt_bytes(&encrypted, "passphrase", b"").unwrap(),
+            b"FOO=bar"
+        );
+        assert!(decrypt_bytes(&encrypted, "wrong passphrase", b"").is_err());
+    }
+
+    #[test]
+    fn test_legacy_blob_decrypts() {
+        let salt = [7u8; SALT_LEN];
+        let nonce = [9u8; NONCE_LEN];
+        let key = derive_key("passphrase", &salt, &KdfParams::LEGACY).unwrap();
+        let ciphertext = XChaCha20Poly1305::new(&key.into())
+            .encrypt(XNonce::from_slice(&nonce), b"FOO=bar".as_slice())
+            .unwrap();
+
+        let mut legacy = vec![LEGACY_BLOB_VERSION];
+        legacy.extend_from_slice(&salt);
+        legacy.extend_from_slice(&nonce);
+        legacy.extend_from_slice(&ciphertext);
+
+        assert_eq!(
+            decrypt_bytes(&legacy, "passphrase", b"ignored").unwrap(),
+            b"FOO=bar"
+        );
+    }
+
+    #[test]
+    fn test_rejects_out_of_range_kdf_params() {
+        let mut encrypted = encrypt_bytes(b"FOO=bar", "passphrase", &TEST_PARAMS, b"").unwrap();
+        encrypted[2..6].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        assert!(decrypt_bytes(&encrypted, "passphrase", b"").is_err());
+
+        let too_large = KdfParams {
+            memory_kib: MAX_MEMORY_KIB + 1,
+            ..TEST_PARAMS
+        };
+        assert!(too_large.validate().is_err());
+
+        let too_slow = KdfParams {
+            iterations: MAX_ITERATIONS + 1,
+            ..TEST_PARAMS
+        };
+        assert!(encrypt_bytes(b"FOO=bar", "passphrase", &too_slow, b"").is_err());
+    }
+
+    #[test]
+    fn test_blob_is_bound_to_its_path() {
+        let prod = object_aad("project", ObjectKind::File, "prod/.env");
+        let dev = object_aad("project", ObjectKind::File, "dev/.env");
+
+        let encrypted = encrypt_bytes(b"FOO=bar", "passphrase", &TEST_PARAMS, &prod).unwrap();
+        assert!(decrypt_bytes(&encrypted, "passphrase", &prod).is_ok());
+        assert!(decrypt_bytes(&encrypted, "passphrase", &dev).is_err());
+
+        let key = [3u8; KEY_LEN];
+        let manifest_aad = object_aad("project", ObjectKind::Manifest, "");
+        let commit_aad = object_aad("project", ObjectKind::Commit, "");
+
+        let encrypted = encrypt_bytes_with_key(b"{}", &key, &manifest_aad).unwrap();
+        assert!(decrypt_bytes_with_key(&encrypted, &key, &manifest_aad).is_ok());
+        assert!(decrypt_bytes_with_key(&encrypted, &key, &commit_aad).is_err());
+    }
+}
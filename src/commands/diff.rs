@@ -0,0 +1,272 @@
+use std::collections::BTreeSet;
+use std::fs;
+
+use anyhow::bail;
+use console::style;
+use zeroize::Zeroize;
+
+use crate::{
+    commands::crypto::decrypt_tracked_file,
+    utils::{
+        commit::{load_commit_manifest, read_head, resolve_commit},
+        dotenv::{KeyChange, diff_env, parse_env},
+        manifest::{LazyProjectKey, Manifest, load_manifest},
+        paths::{normalize_path, to_native_path},
+        ui::{print_header, print_info, print_success, print_warn, short_hash},
+        worktree::{DiskState, compare_with_disk},
+    },
+};
+
+/// One side of a comparison: a manifest (staged or from a commit) or the
+/// plaintext files in the working tree, which are tracked by the staged manifest.
+enum Side {
+    Manifest { label: String, manifest: Manifest },
+    Working { manifest: Manifest },
+}
+
+impl Side {
+    fn commit(rev: &str) -> anyhow::Result<Self> {
+        let hash = resolve_commit(rev)?;
+        Ok(Side::Manifest {
+            label: format!("commit {}", short_hash(&hash, 8)),
+            manifest: load_commit_manifest(&hash)?,
+        })
+    }
+
+    fn label(&self) -> &str {
+        match self {
+            Side::Manifest { label, .. } => label,
+            Side::Working { .. } => "working tree",
+        }
+    }
+
+    fn manifest(&self) -> &Manifest {
+        match self {
+            Side::Manifest { manifest, .. } | Side::Working { manifest } => manifest,
+        }
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        match self {
+            Side::Manifest { manifest, .. } => manifest.files.contains_key(path),
+            Side::Working { .. } => to_native_path(path).is_file(),
+        }
+    }
+
+    fn read(&self, path: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        if !self.contains(path) {
+            return Ok(None);
+        }
+
+        let contents = match self {
+            Side::Manifest { manifest, .. } => decrypt_tracked_file(manifest, path)?,
+            Side::Working { .. } => fs::read(to_native_path(path))
+                .map_err(|e| anyhow::anyhow!("Failed to read '{}': {}", path, e))?,
+        };
+
+        Ok(Some(contents))
+    }
+}
+
+/// Whether a path is known to be identical on both sides without decrypting it.
+fn unchanged(
+    old: &Side,
+    new: &Side,
+    path: &str,
+    manifest_key: &mut LazyProjectKey,
+) -> anyhow::Result<bool> {
+    let entry = |side: &Side| side.manifest().files.get(path).cloned();
+
+    match (old, new) {
+        (Side::Manifest { .. }, Side::Manifest { .. }) => {
+            Ok(matches!((entry(old), entry(new)), (Some(a), Some(b)) if a.blob == b.blob))
+        }
+        (Side::Manifest { .. }, Side::Working { .. }) => match entry(old) {
+            Some(entry) => Ok(compare_with_disk(path, &entry, manifest_key)? == DiskState::Matches),
+            None => Ok(false),
+        },
+        _ => Ok(false),
+    }
+}
+
+fn parse_side(contents: Option<Vec<u8>>, path: &str) -> Option<Vec<(String, String)>> {
+    let mut contents = contents?;
+    let parsed = parse_env(&contents, path).ok();
+    contents.zeroize();
+    parsed
+}
+
+fn print_change(change: &KeyChange, show_values: bool) {
+    match change {
+        KeyChange::Added { key, value } => {
+            let line = if show_values {
+                format!("+ {}={}", key, value)
+            } else {
+                format!("+ {}", key)
+            };
+            println!("  {}", style(line).green());
+        }
+        KeyChange::Removed { key, value } => {
+            let line = if show_values {
+                format!("- {}={}", key, value)
+            } else {
+                format!("- {}", key)
+            };
+            println!("  {}", style(line).red());
+        }
+        KeyChange::Changed { key, old, new } => {
+            let line = if show_values {
+                format!("~ {}={} -> {}", key, old, new)
+            } else {
+                format!("~ {}", key)
+            };
+            println!("  {}", style(line).yellow());
+        }
+    }
+}
+
+pub fn diff(
+    revisions: &[String],
+    staged: bool,
+    paths: &[String],
+    show_values: bool,
+) -> anyhow::Result<()> {
+    let (old, new) = match (revisions, staged) {
+        ([], false) => (
+            Side::Manifest {
+                label: "staged".to_string(),
+                manifest: load_manifest()?,
+            },
+            Side::Working {
+                manifest: load_manifest()?,
+            },
+        ),
+        ([], true) => {
+            let head = match read_head() {
+                Some(head) => Side::commit(&head)?,
+                None => Side::Manifest {
+                    label: "HEAD".to_string(),
+                    manifest: Manifest::new(),
+                },
+            };
+            let staged = Side::Manifest {
+                label: "staged".to_string(),
+                manifest: load_manifest()?,
+            };
+            (head, staged)
+        }
+        ([rev], false) => (
+            Side::commit(rev)?,
+            Side::Working {
+                manifest: load_manifest()?,
+            },
+        ),
+        ([rev], true) => (
+            Side::commit(rev)?,
+            Side::Manifest {
+                label: "staged".to_string(),
+                manifest: load_manifest()?,
+            },
+        ),
+        ([from, to], false) => (Side::commit(from)?, Side::commit(to)?),
+        (_, true) => bail!("`--staged` compares against at most one commit."),
+        _ => bail!("Expected at most two commits."),
+    };
+
+    let selected: BTreeSet<String> = paths.iter().map(|p| normalize_path(p)).collect();
+    let candidates: BTreeSet<String> = old
+        .manifest()
+        .files
+        .keys()
+        .chain(new.manifest().files.keys())
+        .filter(|path| selected.is_empty() || selected.contains(*path))
+        .cloned()
+        .collect();
+
+    let mut manifest_key = LazyProjectKey::default();
+
+    print_info(&format!("Comparing {} -> {}", old.label(), new.label()));
+
+    let mut changed_files = 0;
+
+    for path in &candidates {
+        if !old.contains(path) && !new.contains(path) {
+            continue;
+        }
+        if unchanged(&old, &new, path, &mut manifest_key)? {
+            continue;
+        }
+
+        let (mut old_contents, mut new_contents) = match (old.read(path), new.read(path)) {
+            (Ok(old_contents), Ok(new_contents)) => (old_contents, new_contents),
+            (old_result, new_result) => {
+                // The side that was read may hold decrypted secrets.
+                let mut error = None;
+                for result in [old_result, new_result] {
+                    match result {
+                        Ok(mut contents) => contents.zeroize(),
+                        Err(e) => {
+                            error.get_or_insert(e);
+                        }
+                    }
+                }
+                if let Some(e) = error {
+                    print_warn(&format!("Could not read {}: {}, skipping", path, e));
+                }
+                continue;
+            }
+        };
+
+        if old_contents == new_contents {
+            old_contents.zeroize();
+            new_contents.zeroize();
+            continue;
+        }
+
+        let title = match (&old_contents, &new_contents) {
+            (None, _) => format!("{} (added)", path),
+            (_, None) => format!("{} (removed)", path),
+            _ => path.clone(),
+        };
+        let was_present = old_contents.is_some();
+        let is_present = new_contents.is_some();
+
+        let old_vars = parse_side(old_contents, path);
+        let new_vars = parse_side(new_contents, path);
+
+        changed_files += 1;
+        print_header(&title);
+
+        match (old_vars, new_vars) {
+            (Some(old_vars), Some(new_vars)) => {
+                let changes = diff_env(&old_vars, &new_vars);
+                if changes.is_empty() {
+                    print_info("Only comments or formatting changed.");
+                }
+                for change in &changes {
+                    print_change(change, show_values);
+                }
+            }
+            (None, Some(new_vars)) if !was_present => {
+                for change in &diff_env(&[], &new_vars) {
+                    print_change(change, show_values);
+                }
+            }
+            (Some(old_vars), None) if !is_present => {
+                for change in &diff_env(&old_vars, &[]) {
+                    print_change(change, show_values);
+                }
+            }
+            _ => print_info("Contents differ (not a dotenv file)."),
+        }
+    }
+
+    println!();
+    if changed_files == 0 {
+        print_success("No differences.");
+    } else {
+        print_info(&format!("{} file(s) changed.", changed_files));
+    }
+
+    Ok(())
+}
@@ -3,12 +3,15 @@ use std::path::Path;
 use console::style;
 
 use crate::utils::{
-    commit::{commit_exists, commits_ahead_of_remote, read_head, read_remote_head},
+    commit::{
+        commit_exists, commits_ahead_of_remote, read_head, read_remote_head, upstream_branch,
+    },
     config::load_token,
-    manifest::{get_current_manifest_hash, load_manifest, read_applied},
-    project_config::{get_remote_url, load_project_config},
+    manifest::{Manifest, get_current_manifest_hash, load_manifest, read_applied},
+    project_config::{ProjectConfig, get_remote_url, load_project_config},
     storage::fetch_remote_head,
     ui::{print_error, print_header, print_info, print_kv, print_success, print_warn},
+    worktree::{TrackingRules, excluded_tracked, untracked_files},
 };
 
 struct DoctorReport {
@@ -212,9 +215,13 @@ pub async fn doctor(remote: Option<&str>) -> anyhow::Result<()> {
             let missing_blobs: Vec<_> = manifest
                 .files
                 .iter()
-                .filter(|(_, hash)| !cache_blob_exists(hash))
+                .filter(|(_, entry)| !cache_blob_exists(&entry.blob))
                 .collect();
 
+            if let Some(project) = &project {
+                check_tracking_rules(project, &manifest, &mut report);
+            }
+
             if missing_blobs.is_empty() {
                 report.ok("All tracked file blobs exist in cache.");
             } else {
@@ -222,8 +229,8 @@ pub async fn doctor(remote: Option<&str>) -> anyhow::Result<()> {
                     "{} tracked file blob(s) are missing from cache.",
                     missing_blobs.len()
                 ));
-                for (path, hash) in missing_blobs.iter().take(5) {
-                    print_info(&format!("{} -> {}", path, short_hash(hash)));
+                for (path, entry) in missing_blobs.iter().take(5) {
+                    print_info(&format!("{} -> {}", path, short_hash(&entry.blob)));
                 }
                 if missing_blobs.len() > 5 {
                     print_info(&format!("...and {} more.", missing_blobs.len() - 5));
@@ -240,7 +247,15 @@ pub async fn doctor(remote: Option<&str>) -> anyhow::Result<()> {
     }
 
     if let (Some(token), Some(project), Some(server)) = (&token, &project, &server) {
-        match fetch_remote_head(&client, server, token, &project.project_id).await {
+        match fetch_remote_head(
+            &client,
+            server,
+            token,
+            &project.project_id,
+            &upstream_branch(),
+        )
+        .await
+        {
             Ok(server_head) => {
                 report.ok("Remote HEAD request succeeded.");
                 match server_head {
@@ -289,6 +304,40 @@ pub async fn doctor(remote: Option<&str>) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn check_tracking_rules(project: &ProjectConfig, manifest: &Manifest, report: &mut DoctorReport) {
+    let rules = match TrackingRules::load(project) {
+        Ok(rules) => rules,
+        Err(error) => {
+            report.error(&format!("Tracking rules are invalid: {}", error));
+            return;
+        }
+    };
+
+    let untracked = untracked_files(Path::new("."), manifest, &rules);
+    if untracked.is_empty() {
+        report.ok("Every file matching the tracking rules is tracked.");
+    } else {
+        report.warn(&format!(
+            "{} file(s) match the tracking rules but are not tracked. Run `envy add`.",
+            untracked.len()
+        ));
+        for path in untracked.iter().take(5) {
+            print_info(path);
+        }
+    }
+
+    let excluded = excluded_tracked(manifest, &rules);
+    if !excluded.is_empty() {
+        report.warn(&format!(
+            "{} tracked file(s) match exclude rules.",
+            excluded.len()
+        ));
+        for path in excluded.iter().take(5) {
+            print_info(path);
+        }
+    }
+}
+
 fn print_summary(report: &DoctorReport) {
     println!();
     if report.errors == 0 && report.warnings == 0 {
@@ -0,0 +1,77 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::bail;
+
+use crate::{
+    commands::vars::edit_tracked_file,
+    utils::paths::{normalize_path, set_file_mode},
+};
+
+fn editor_command() -> Vec<String> {
+    let default = if cfg!(windows) { "notepad" } else { "vi" };
+
+    std::env::var("VISUAL")
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .or_else(|| std::env::var("EDITOR").ok())
+        .filter(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| default.to_string())
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Writes `contents` to a private temporary file, opens it in the user's
+/// editor and returns what was saved. The file is overwritten and removed
+/// before returning.
+fn edit_in_editor(path: &str, contents: &str) -> anyhow::Result<String> {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let mut file = tempfile::Builder::new()
+        .prefix("envy-")
+        .suffix(&format!("-{}", name))
+        .tempfile()?;
+    set_file_mode(file.path(), 0o600)?;
+
+    file.write_all(contents.as_bytes())?;
+    file.flush()?;
+
+    let editor = editor_command();
+    let (program, args) = editor
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("No editor configured. Set $EDITOR."))?;
+
+    let status = Command::new(program)
+        .args(args)
+        .arg(file.path())
+        .status()
+        .map_err(|e| anyhow::anyhow!("Failed to launch editor '{}': {}", program, e));
+
+    let edited = status.and_then(|status| {
+        if !status.success() {
+            bail!("Editor exited with {}; '{}' was not changed.", status, path);
+        }
+        std::fs::read_to_string(file.path())
+            .map_err(|e| anyhow::anyhow!("Failed to read edited file: {}", e))
+    });
+
+    // Editors may replace the file rather than write to it, so scrub by path.
+    let _ = overwrite(file.path());
+    file.close()?;
+
+    edited
+}
+
+fn overwrite(path: &Path) -> std::io::Result<()> {
+    let len = std::fs::metadata(path)?.len();
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    file.write_all(&vec![0u8; len as usize])?;
+    file.sync_all()
+}
+
+pub fn edit(path: &str, message: Option<&str>) -> anyhow::Result<()> {
+    let path = normalize_path(path);
+    edit_tracked_file(&path, message, |contents| edit_in_editor(&path, contents))
+}
@@ -0,0 +1,106 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use anyhow::bail;
+use console::style;
+use zeroize::Zeroize;
+
+use crate::{
+    commands::crypto::decrypt_tracked_file,
+    utils::{
+        dotenv::{parse_env, template},
+        manifest::load_manifest,
+        paths::{ensure_parent_exists, normalize_path},
+        project_config::load_project_config,
+        schema::load_schema,
+        ui::{print_error, print_success},
+    },
+};
+
+fn keys(contents: &[u8], source: &str) -> anyhow::Result<BTreeSet<String>> {
+    let mut vars = parse_env(contents, source)?;
+    let keys = vars.iter().map(|(key, _)| key.clone()).collect();
+    for (_, value) in vars.iter_mut() {
+        value.zeroize();
+    }
+    Ok(keys)
+}
+
+/// Writes a copy of a tracked file with every value replaced by its schema
+/// default (or left empty), or with `check`, verifies that an existing example
+/// declares the same keys as the tracked file.
+pub fn example(file: &str, output: Option<&str>, check: bool) -> anyhow::Result<()> {
+    let manifest = load_manifest()?;
+    let path = normalize_path(file);
+    let output = output
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{}.example", path));
+
+    let mut plaintext = decrypt_tracked_file(&manifest, &path)?;
+
+    if check {
+        let tracked = keys(&plaintext, &path);
+        plaintext.zeroize();
+        let tracked = tracked?;
+
+        let existing =
+            fs::read(&output).map_err(|e| anyhow::anyhow!("Failed to read '{}': {}", output, e))?;
+        let documented = keys(&existing, &output)?;
+
+        let missing: Vec<&String> = tracked.difference(&documented).collect();
+        let stale: Vec<&String> = documented.difference(&tracked).collect();
+
+        for key in &missing {
+            print_error(&format!(
+                "{} is set in '{}' but missing from '{}'",
+                key, path, output
+            ));
+        }
+        for key in &stale {
+            print_error(&format!(
+                "{} is in '{}' but no longer set in '{}'",
+                key, output, path
+            ));
+        }
+
+        if !missing.is_empty() || !stale.is_empty() {
+            bail!(
+                "'{}' is out of date. Run {} to regenerate it.",
+                output,
+                style(format!("`envy example -f {} -o {}`", path, output)).cyan()
+            );
+        }
+
+        print_success(&format!("'{}' matches '{}'.", output, path));
+        return Ok(());
+    }
+
+    let rules = match load_schema()? {
+        Some(schema) => schema.rules_for(&path, &load_project_config()?)?,
+        None => Default::default(),
+    };
+
+    let contents = String::from_utf8(std::mem::take(&mut plaintext)).map_err(|e| {
+        e.into_bytes().zeroize();
+        anyhow::anyhow!("'{}' is not valid UTF-8", path)
+    });
+    let mut contents = contents?;
+
+    let example = template(&contents, |key| match rules.get(key) {
+        Some(spec) => (
+            spec.default.clone().unwrap_or_default(),
+            spec.description.clone(),
+        ),
+        None => (String::new(), None),
+    });
+    contents.zeroize();
+
+    let output_path = Path::new(&output);
+    ensure_parent_exists(output_path)
+        .and_then(|_| fs::write(output_path, example))
+        .map_err(|e| anyhow::anyhow!("Failed to write '{}': {}", output, e))?;
+
+    print_success(&format!("Wrote '{}' from '{}'.", output, path));
+    Ok(())
+}
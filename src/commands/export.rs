@@ -0,0 +1,53 @@
+use std::io::Write;
+
+use zeroize::Zeroize;
+
+use crate::{
+    commands::crypto::decrypt_tracked_file,
+    utils::{
+        commit::load_commit_manifest,
+        dotenv::parse_env,
+        export::{ExportFormat, render},
+        manifest::load_manifest,
+        paths::normalize_path,
+        project_config::load_project_config,
+    },
+};
+
+pub fn export(
+    path: &str,
+    format: ExportFormat,
+    commit: Option<&str>,
+    name: Option<&str>,
+) -> anyhow::Result<()> {
+    let manifest = match commit {
+        Some(rev) => load_commit_manifest(rev)?,
+        None => load_manifest()?,
+    };
+
+    let normalized = normalize_path(path);
+    let mut plaintext = decrypt_tracked_file(&manifest, &normalized)?;
+    let parsed = parse_env(&plaintext, &normalized);
+    plaintext.zeroize();
+    let mut vars = parsed?;
+
+    let secret_name = match name {
+        Some(name) => name.to_string(),
+        None => load_project_config()?.name.unwrap_or_default(),
+    };
+
+    let rendered = render(&vars, format, &secret_name);
+    for (_, value) in vars.iter_mut() {
+        value.zeroize();
+    }
+    let mut rendered = rendered?;
+
+    let mut stdout = std::io::stdout().lock();
+    let result = stdout
+        .write_all(rendered.as_bytes())
+        .and_then(|_| stdout.flush())
+        .map_err(|e| anyhow::anyhow!("Failed to write to stdout: {}", e));
+    rendered.zeroize();
+
+    result
+}
@@ -0,0 +1,281 @@
+use std::collections::BTreeSet;
+use std::fs;
+
+use anyhow::bail;
+use console::style;
+
+use crate::{
+    commands::{
+        branch::switch,
+        commit::commit,
+        restore::{fetch_missing_blobs, load_commit_manifest_fetching, write_tracked_file},
+    },
+    utils::{
+        commit::{
+            DEFAULT_BRANCH, detach_head, ensure_attached, is_known_ancestor, load_commit,
+            load_commit_manifest, read_branch, read_detached, read_head, read_remote_head,
+            resolve_commit, validate_branch_name, write_head,
+        },
+        manifest::{
+            LazyProjectKey, Manifest, compute_manifest_content_hash, get_current_manifest_hash,
+            load_manifest, save_manifest, set_manifest, write_applied,
+        },
+        paths::to_native_path,
+        ui::{print_info, print_item, print_kv, print_success, print_warn, short_hash},
+        worktree::{DiskState, FileStatus, compare_with_disk, tracked_statuses},
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetMode {
+    /// Move HEAD only.
+    Soft,
+    /// Move HEAD and the staged manifest.
+    Mixed,
+    /// Move HEAD and the staged manifest, and rewrite the working tree.
+    Hard,
+}
+
+/// Refuses to `action` while the staged manifest differs from HEAD or a
+/// tracked file has plaintext edits that were not encrypted.
+fn ensure_clean(action: &str) -> anyhow::Result<()> {
+    let staged = load_manifest()?;
+    let head = match read_head() {
+        Some(head) => Some(load_commit_manifest(&head)?),
+        None => None,
+    };
+
+    if head.as_ref().map(compute_manifest_content_hash)
+        != Some(compute_manifest_content_hash(&staged))
+    {
+        bail!(
+            "The staged manifest has uncommitted changes. Commit them or discard them with `envy reset --hard` before you {}.",
+            action
+        );
+    }
+
+    let modified: Vec<String> =
+        tracked_statuses(&staged, head.as_ref(), &mut LazyProjectKey::default(), None)?
+            .into_iter()
+            .filter(|(_, status)| *status == FileStatus::Modified)
+            .map(|(path, _)| path)
+            .collect();
+
+    if !modified.is_empty() {
+        bail!(
+            "Local changes to {} would be overwritten. Encrypt and commit them, or discard them with `envy reset --hard`, before you {}.",
+            modified.join(", "),
+            action
+        );
+    }
+
+    Ok(())
+}
+
+/// Makes the working tree match `to`: writes every file whose plaintext
+/// differs and deletes files tracked by `from` that `to` no longer tracks.
+async fn apply_to_worktree(from: &Manifest, to: &Manifest) -> anyhow::Result<()> {
+    let mut manifest_key = LazyProjectKey::default();
+
+    let mut changed = Vec::new();
+    for (path, entry) in &to.files {
+        if compare_with_disk(path, entry, &mut manifest_key)? != DiskState::Matches {
+            changed.push(path.clone());
+        }
+    }
+
+    fetch_missing_blobs(to, &changed).await?;
+
+    for path in &changed {
+        write_tracked_file(to, path)?;
+        print_item(&format!("{} {}", style("updated").cyan(), path));
+    }
+
+    for path in from
+        .files
+        .keys()
+        .filter(|path| !to.files.contains_key(*path))
+    {
+        let target = to_native_path(path);
+        if target.is_file() {
+            fs::remove_file(&target)
+                .map_err(|e| anyhow::anyhow!("Failed to remove '{}': {}", path, e))?;
+            print_item(&format!("{} {}", style("removed").red(), path));
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a commit's files to the working tree and moves the staged manifest
+/// to it, refusing if that would discard uncommitted work. HEAD is left alone.
+pub async fn move_worktree(target: &str, action: &str) -> anyhow::Result<()> {
+    if read_head().as_deref() == Some(target) {
+        return Ok(());
+    }
+    ensure_clean(action)?;
+
+    let manifest = load_commit_manifest_fetching(target).await?;
+    apply_to_worktree(&load_manifest()?, &manifest).await?;
+
+    let manifest_hash = load_commit(target)?.manifest_hash;
+    set_manifest(&manifest_hash)?;
+    write_applied(&manifest_hash)?;
+
+    Ok(())
+}
+
+/// Checks out a commit without a branch. Commit, revert, push and pull are
+/// blocked until a branch is switched to again. Branch names are switched to.
+pub async fn checkout(rev: &str) -> anyhow::Result<()> {
+    if validate_branch_name(rev).is_ok() && read_branch(rev).is_some() {
+        return switch(rev, false).await;
+    }
+
+    if read_head().is_none() {
+        bail!("No commits yet.");
+    }
+    let target = resolve_commit(rev)?;
+
+    move_worktree(&target, "check out another commit").await?;
+    detach_head(&target)?;
+
+    let branch = read_detached().unwrap_or_else(|| DEFAULT_BRANCH.to_string());
+    print_kv("HEAD", short_hash(&target, 12));
+    print_success(&format!(
+        "Checked out {} \"{}\".",
+        short_hash(&target, 8),
+        load_commit(&target)?.message
+    ));
+    print_warn(&format!(
+        "HEAD is detached. Commit, revert, push and pull are disabled until you run {}.",
+        style(format!("`envy switch {}`", branch)).cyan()
+    ));
+
+    Ok(())
+}
+
+pub async fn reset(rev: &str, mode: ResetMode) -> anyhow::Result<()> {
+    let Some(head) = read_head() else {
+        bail!("No commits yet.");
+    };
+    let target = resolve_commit(rev)?;
+    let target_commit = load_commit(&target)?;
+
+    if mode != ResetMode::Soft {
+        let manifest = load_commit_manifest_fetching(&target).await?;
+        if mode == ResetMode::Hard {
+            apply_to_worktree(&load_manifest()?, &manifest).await?;
+            write_applied(&target_commit.manifest_hash)?;
+        }
+        set_manifest(&target_commit.manifest_hash)?;
+    }
+
+    write_head(&target)?;
+
+    print_kv("HEAD", short_hash(&target, 12));
+    print_kv("Previous HEAD", short_hash(&head, 12));
+
+    match mode {
+        ResetMode::Soft => print_info("The staged manifest was kept."),
+        ResetMode::Mixed => print_info(&format!(
+            "Files on disk were not changed. Run {} to see how they differ.",
+            style("`envy status`").cyan()
+        )),
+        ResetMode::Hard => {}
+    }
+    print_success(&format!(
+        "HEAD is now at {} \"{}\".",
+        short_hash(&target, 8),
+        target_commit.message
+    ));
+
+    if read_detached().is_none()
+        && let Some(remote) = read_remote_head()
+        && !is_known_ancestor(&remote, &target)
+    {
+        print_warn(&format!(
+            "origin/HEAD {} is no longer in the history of HEAD. Other clients refuse rewound history, so undo pushed commits with {} instead.",
+            short_hash(&remote, 8),
+            style("`envy revert`").cyan()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Records a new commit that undoes the changes `rev` made to its parent. Files
+/// changed again by later commits are reported instead of being overwritten.
+pub async fn revert(
+    rev: &str,
+    message: Option<&str>,
+    author: Option<String>,
+    no_verify: bool,
+) -> anyhow::Result<()> {
+    ensure_attached("revert")?;
+    let target = resolve_commit(rev)?;
+    let target_commit = load_commit(&target)?;
+
+    ensure_clean("revert")?;
+
+    let after = load_commit_manifest_fetching(&target).await?;
+    let before = match &target_commit.parent {
+        Some(parent) => load_commit_manifest_fetching(parent).await?,
+        None => Manifest::new(),
+    };
+
+    let current = load_manifest()?;
+    let mut reverted = load_manifest()?;
+    let mut conflicts = Vec::new();
+
+    let paths: BTreeSet<&String> = before.files.keys().chain(after.files.keys()).collect();
+    for path in paths {
+        let old = before.files.get(path);
+        let new = after.files.get(path);
+        if old == new {
+            continue;
+        }
+        if current.files.get(path) != new {
+            conflicts.push(path.clone());
+            continue;
+        }
+
+        match old {
+            Some(entry) => reverted.files.insert(path.clone(), entry.clone()),
+            None => reverted.files.remove(path),
+        };
+    }
+
+    if !conflicts.is_empty() {
+        bail!(
+            "Cannot revert {}: {} changed again in later commits.",
+            short_hash(&target, 8),
+            conflicts.join(", ")
+        );
+    }
+
+    if reverted.files == current.files {
+        print_info(&format!(
+            "Commit {} has no changes to revert.",
+            short_hash(&target, 8)
+        ));
+        return Ok(());
+    }
+
+    let previous = get_current_manifest_hash();
+    save_manifest(&reverted)?;
+
+    let message = message
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("Revert \"{}\"", target_commit.message));
+    if let Err(e) = commit(&message, author, no_verify) {
+        if let Some(previous) = previous {
+            set_manifest(&previous)?;
+        }
+        return Err(e);
+    }
+
+    apply_to_worktree(&current, &reverted).await?;
+
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::bail;
+use console::style;
+use zeroize::Zeroize;
+
+use crate::{
+    commands::crypto::{FileKey, store_tracked_file},
+    utils::{
+        import::{ImportFormat, convert_to_dotenv},
+        manifest::{Manifest, load_manifest, save_manifest},
+        paths::normalize_path,
+        ui::{print_info, print_success},
+        worktree::{build_globset, matches_path, walk_files},
+    },
+};
+
+fn import_one(
+    manifest: &mut Manifest,
+    source: &Path,
+    format: ImportFormat,
+    tracked: &str,
+    key: &FileKey,
+) -> anyhow::Result<()> {
+    let description = source.display().to_string();
+    let mut contents =
+        fs::read(source).map_err(|e| anyhow::anyhow!("Failed to read '{}': {}", description, e))?;
+
+    let converted = convert_to_dotenv(&contents, format, &description);
+    contents.zeroize();
+    let mut converted = converted?;
+
+    let stored = store_tracked_file(manifest, tracked, converted.as_bytes(), None, key);
+    converted.zeroize();
+    stored?;
+
+    print_success(&format!("Imported '{}' as '{}'.", description, tracked));
+    Ok(())
+}
+
+/// Converts `source` (or every `.env*` file under `recursive`) to dotenv and
+/// encrypts it straight into the manifest, without writing plaintext to disk.
+pub fn import(
+    format: ImportFormat,
+    source: Option<&str>,
+    as_path: Option<&str>,
+    recursive: Option<&str>,
+    key: &FileKey,
+) -> anyhow::Result<()> {
+    let mut manifest = load_manifest()?;
+    let mut imported = 0;
+
+    match (source, recursive) {
+        (Some(source), None) => {
+            let tracked = match as_path {
+                Some(path) => normalize_path(path),
+                None => bail!("`--as` is required to name the tracked file."),
+            };
+            import_one(&mut manifest, Path::new(source), format, &tracked, key)?;
+            imported += 1;
+        }
+        (None, Some(dir)) => {
+            let root = Path::new(dir);
+            if !root.is_dir() {
+                bail!("'{}' is not a directory", dir);
+            }
+
+            let env_files = build_globset(&[".env*".to_string()])?;
+            let mut found: Vec<String> = walk_files(root)
+                .into_iter()
+                .filter(|path| matches_path(&env_files, path))
+                .collect();
+            found.sort();
+
+            if found.is_empty() {
+                bail!("No .env* files found under '{}'.", dir);
+            }
+
+            // A directory inside the project keeps its own path; one outside it
+            // is imported at the root unless `--as` gives a prefix.
+            let inside_project = root.is_relative()
+                && !root
+                    .components()
+                    .any(|c| matches!(c, std::path::Component::ParentDir));
+            let prefix = match as_path {
+                Some(path) => normalize_path(path),
+                None if inside_project => normalize_path(dir),
+                None => String::new(),
+            };
+            for relative in &found {
+                let tracked = if prefix.is_empty() {
+                    relative.clone()
+                } else {
+                    format!("{}/{}", prefix, relative)
+                };
+                import_one(&mut manifest, &root.join(relative), format, &tracked, key)?;
+                imported += 1;
+            }
+        }
+        (Some(_), Some(_)) => bail!("Give either a source file or `--recursive`, not both."),
+        (None, None) => bail!("Give a source file or `--recursive <dir>`."),
+    }
+
+    save_manifest(&manifest)?;
+
+    println!();
+    print_info(&format!(
+        "{} file(s) imported. Run {} to record them.",
+        imported,
+        style("`envy commit -m \"message\"`").cyan()
+    ));
+
+    Ok(())
+}
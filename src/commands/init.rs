@@ -42,6 +42,8 @@ pub fn ensure_gitignore() -> anyhow::Result<()> {
 .envoy/cache/
 .envoy/latest
 .envoy/HEAD
+.envoy/DETACHED
+.envoy/REKEY
 .envoy/refs/
 
 # Envoy - Config
@@ -1,12 +1,27 @@
+pub mod add;
 pub mod auth;
+pub mod branch;
+pub mod cat;
 pub mod commit;
 pub mod crypto;
+pub mod diff;
 pub mod doctor;
+pub mod edit;
+pub mod example;
+pub mod export;
+pub mod history;
+pub mod import;
 pub mod init;
 pub mod member;
 pub mod pull;
 pub mod push;
+pub mod rekey;
 pub mod remote;
 pub mod remove;
+pub mod restore;
+pub mod run;
 pub mod status;
+pub mod tag;
 pub mod update;
+pub mod validate;
+pub mod vars;
@@ -1,39 +1,113 @@
 use std::path::Path;
 
+use anyhow::bail;
+use console::style;
+
 use crate::{
-    commands::crypto::decrypt_bytes,
+    commands::{
+        crypto::{ObjectKind, decrypt_bytes, decrypt_with_wrapped_key, object_aad, read_blob},
+        rekey::ensure_no_pending_rekey,
+        tag::fetch_tags,
+    },
     utils::{
         commit::{
-            commit_exists, load_commit, read_head, read_remote_head, write_head, write_remote_head,
+            DEFAULT_BRANCH, commit_exists, detach_head, ensure_attached, is_ancestor,
+            is_known_ancestor, load_commit, read_accepted_heads, read_detached, read_head,
+            read_remote_head, resolve_commit, upstream_branch, write_head, write_remote_head,
         },
         config::load_token,
-        manifest::{load_manifest, read_applied, set_manifest, write_applied},
-        paths::{ensure_parent_exists, normalize_path, to_native_path},
+        manifest::{
+            FileEntry, Manifest, get_project_key, load_manifest, read_applied, set_manifest,
+            write_applied,
+        },
+        paths::{ensure_parent_exists, normalize_path, set_file_mode, to_native_path},
+        profile::{Profile, load_profile},
         project_config::{get_remote_url, load_project_config},
         storage::{download_blob, download_commit, download_manifest, fetch_remote_head},
         ui::{
             PassphraseResult, create_progress_bar, create_spinner, print_header, print_info,
-            print_kv, print_success, print_warn, prompt_file_passphrase,
+            print_kv, print_success, print_warn, prompt_file_passphrase, short_hash,
         },
     },
 };
 
-pub async fn pull(remote: Option<&str>) -> anyhow::Result<()> {
+pub async fn pull(
+    remote: Option<&str>,
+    allow_rewind: bool,
+    env: Option<&str>,
+    git_ref: Option<&str>,
+) -> anyhow::Result<()> {
+    if git_ref.is_none() {
+        ensure_attached("pull")?;
+    }
+    ensure_no_pending_rekey("pull")?;
     let token = load_token()?;
     let project = load_project_config()?;
     let server = get_remote_url(&project, remote)?;
+    let profile = load_profile(&project, env)?;
 
     let client = reqwest::Client::new();
+    let branch = upstream_branch();
+
+    // Tags are secondary to the branch, so a failure here does not stop the pull.
+    if let Err(e) = fetch_tags(&client, &server, &token, &project.project_id).await {
+        print_warn(&format!("Could not fetch tags: {}", e));
+    }
+
+    if let Some(rev) = git_ref {
+        return pull_ref(
+            &client,
+            &server,
+            &token,
+            &project.project_id,
+            rev,
+            profile.as_ref(),
+        )
+        .await;
+    }
 
     let remote_head_result =
-        fetch_remote_head(&client, &server, &token, &project.project_id).await?;
+        fetch_remote_head(&client, &server, &token, &project.project_id, &branch).await?;
 
     if let Some(ref remote_head) = remote_head_result {
-        return pull_with_commits(&client, &server, &token, &project.project_id, remote_head).await;
+        return pull_with_commits(
+            &client,
+            &server,
+            &token,
+            &project.project_id,
+            remote_head,
+            allow_rewind,
+            profile.as_ref(),
+        )
+        .await;
+    }
+
+    if branch != DEFAULT_BRANCH {
+        bail!(
+            "Branch '{}' does not exist on the remote. Run `envy push` to publish it.",
+            branch
+        );
+    }
+
+    if let Some(previous) = read_remote_head() {
+        reject_rewind(
+            &format!(
+                "Remote has no HEAD, but origin/HEAD was {}.",
+                short_hash(&previous, 8)
+            ),
+            allow_rewind,
+        )?;
     }
 
     // Fall back to legacy manifest-based pull
-    legacy_pull(&client, &server, &token, &project.project_id).await
+    legacy_pull(
+        &client,
+        &server,
+        &token,
+        &project.project_id,
+        profile.as_ref(),
+    )
+    .await
 }
 
 async fn pull_with_commits(
@@ -42,10 +116,14 @@ async fn pull_with_commits(
     token: &str,
     project_id: &str,
     remote_head: &str,
+    allow_rewind: bool,
+    profile: Option<&Profile>,
 ) -> anyhow::Result<()> {
     let local_remote_head = read_remote_head();
 
-    if local_remote_head.as_ref() == Some(&remote_head.to_string()) {
+    // A profile pull always restores its files, so one checkout can be
+    // switched between environments without new commits.
+    if profile.is_none() && local_remote_head.as_ref() == Some(&remote_head.to_string()) {
         let local_head = read_head();
         if local_head.as_ref() == Some(&remote_head.to_string()) {
             print_success("Already up to date.");
@@ -55,171 +133,191 @@ async fn pull_with_commits(
 
     print_header("Fetching commits");
 
-    let mut commits_to_download = Vec::new();
-    let mut current_hash = Some(remote_head.to_string());
-
-    while let Some(hash) = current_hash {
-        if commit_exists(&hash) {
-            break; // We have this commit and all ancestors
-        }
-        commits_to_download.push(hash.clone());
-
-        let spinner = create_spinner(&format!("Fetching commit {}...", &hash[..8]));
-        download_commit(client, server, token, project_id, &hash).await?;
-        spinner.finish_and_clear();
-
-        let commit = load_commit(&hash)?;
-        current_hash = commit.parent;
-    }
+    let fetched = download_history(client, server, token, project_id, remote_head).await?;
 
-    if commits_to_download.is_empty() {
+    if profile.is_none() && fetched == 0 && local_remote_head.as_deref() == Some(remote_head) {
         print_success("Already up to date.");
         return Ok(());
     }
 
-    print_success(&format!("Fetched {} commit(s).", commits_to_download.len()));
+    if fetched > 0 {
+        print_success(&format!("Fetched {} commit(s).", fetched));
+    }
 
-    let latest_commit = load_commit(remote_head)?;
-    let manifest_hash = &latest_commit.manifest_hash;
+    if let Some(previous) = &local_remote_head
+        && previous != remote_head
+    {
+        verify_remote_advance(previous, remote_head, allow_rewind)?;
+    }
 
-    let manifest_blob_path = Path::new(".envoy/cache").join(format!("{}.blob", manifest_hash));
+    let manifest_hash = load_commit(remote_head)?.manifest_hash;
+    restore_commit(client, server, token, project_id, &manifest_hash, profile).await?;
 
-    if !manifest_blob_path.exists() {
-        let spinner = create_spinner("Downloading manifest...");
-        download_manifest(client, server, token, project_id, manifest_hash).await?;
-        spinner.finish_and_clear();
-    }
+    write_head(remote_head)?;
+    write_remote_head(remote_head)?;
+    write_applied(&manifest_hash)?;
 
-    set_manifest(manifest_hash)?;
+    println!();
+    print_kv("HEAD", short_hash(remote_head, 12));
+    print_success(&format!(
+        "Updated to commit {}.",
+        short_hash(remote_head, 8)
+    ));
 
-    let manifest = load_manifest()?;
-    if !manifest.files.is_empty() {
-        print_header(&format!("Pulling {} file(s)", manifest.files.len()));
+    Ok(())
+}
 
-        let pb = create_progress_bar(manifest.files.len() as u64);
-        let mut downloaded = 0;
+/// Restores the files of a tag or commit and detaches HEAD there, so a deploy
+/// can pin an exact version of its secrets.
+async fn pull_ref(
+    client: &reqwest::Client,
+    server: &str,
+    token: &str,
+    project_id: &str,
+    rev: &str,
+    profile: Option<&Profile>,
+) -> anyhow::Result<()> {
+    let target = match resolve_commit(rev) {
+        Ok(hash) => hash,
+        // A full hash can be fetched even if no local ref leads to it.
+        Err(_) if rev.len() == 64 && rev.chars().all(|c| c.is_ascii_hexdigit()) => {
+            rev.to_ascii_lowercase()
+        }
+        Err(e) => return Err(e),
+    };
 
-        for hash in manifest.files.values() {
-            let path = Path::new(".envoy/cache").join(format!("{}.blob", hash));
+    print_header("Fetching commits");
+    let fetched = download_history(client, server, token, project_id, &target).await?;
+    if fetched > 0 {
+        print_success(&format!("Fetched {} commit(s).", fetched));
+    }
 
-            if path.exists() {
-                pb.inc(1);
-                continue;
-            }
+    let manifest_hash = load_commit(&target)?.manifest_hash;
+    restore_commit(client, server, token, project_id, &manifest_hash, profile).await?;
 
-            pb.set_message(format!("Downloading {}...", &hash[..8]));
-            download_blob(client, server, token, project_id, hash).await?;
+    detach_head(&target)?;
+    write_applied(&manifest_hash)?;
 
-            downloaded += 1;
-            pb.inc(1);
-        }
+    let branch = read_detached().unwrap_or_else(|| DEFAULT_BRANCH.to_string());
+    println!();
+    print_kv("HEAD", short_hash(&target, 12));
+    print_success(&format!("Restored {} ({}).", rev, short_hash(&target, 8)));
+    print_warn(&format!(
+        "HEAD is detached. Run {} to return to the branch.",
+        style(format!("`envy switch {}`", branch)).cyan()
+    ));
 
-        pb.finish_and_clear();
+    Ok(())
+}
 
-        if downloaded > 0 {
-            print_success(&format!("Downloaded {} file(s).", downloaded));
-        }
+/// Downloads `head` and its ancestors until a commit that is already cached,
+/// returning how many were fetched.
+async fn download_history(
+    client: &reqwest::Client,
+    server: &str,
+    token: &str,
+    project_id: &str,
+    head: &str,
+) -> anyhow::Result<usize> {
+    let mut fetched = 0;
+    let mut current_hash = Some(head.to_string());
 
-        let pb = create_progress_bar(manifest.files.len() as u64);
-        pb.set_message("Restoring files...");
-        let mut restored = 0;
-        let mut skipped = 0;
+    while let Some(hash) = current_hash {
+        if commit_exists(&hash) {
+            break; // We have this commit and all ancestors
+        }
 
-        for (file_path, hash) in &manifest.files {
-            let blob_path = Path::new(".envoy/cache").join(format!("{}.blob", hash));
-            let encrypted = tokio::fs::read(&blob_path)
-                .await
-                .map_err(|e| anyhow::anyhow!("Failed to read blob for '{}': {}", file_path, e))?;
+        let spinner = create_spinner(&format!("Fetching commit {}...", short_hash(&hash, 8)));
+        download_commit(client, server, token, project_id, &hash).await?;
+        spinner.finish_and_clear();
+        fetched += 1;
 
-            pb.suspend(|| {
-                println!();
-            });
+        let commit = load_commit(&hash)?;
+        current_hash = commit.parent;
+    }
 
-            let passphrase = match prompt_file_passphrase(file_path) {
-                Ok(PassphraseResult::Passphrase(pass)) => pass,
-                Ok(PassphraseResult::Skip) => {
-                    pb.suspend(|| {
-                        print_info(&format!("Skipping '{}'", file_path));
-                    });
-                    skipped += 1;
-                    pb.inc(1);
-                    continue;
-                }
-                Err(e) => {
-                    pb.suspend(|| {
-                        print_warn(&format!(
-                            "Failed to read passphrase for '{}': {}",
-                            file_path, e
-                        ));
-                    });
-                    skipped += 1;
-                    pb.inc(1);
-                    continue;
-                }
-            };
+    Ok(fetched)
+}
 
-            match decrypt_bytes(&encrypted, &passphrase) {
-                Ok(plaintext) => {
-                    let normalized = normalize_path(file_path);
-                    let target_path = to_native_path(&normalized);
-
-                    if let Err(e) = ensure_parent_exists(&target_path) {
-                        pb.suspend(|| {
-                            print_warn(&format!(
-                                "Failed to create directory for '{}': {}",
-                                file_path, e
-                            ));
-                        });
-                        skipped += 1;
-                        pb.inc(1);
-                        continue;
-                    }
+/// Stages a commit's manifest, downloading it if needed, and restores its files
+/// (only the profile's, if one is given).
+async fn restore_commit(
+    client: &reqwest::Client,
+    server: &str,
+    token: &str,
+    project_id: &str,
+    manifest_hash: &str,
+    profile: Option<&Profile>,
+) -> anyhow::Result<()> {
+    let manifest_blob_path = Path::new(".envoy/cache").join(format!("{}.blob", manifest_hash));
 
-                    if let Err(e) = tokio::fs::write(&target_path, plaintext).await {
-                        pb.suspend(|| {
-                            print_warn(&format!(
-                                "Failed to write '{}': {}",
-                                target_path.display(),
-                                e
-                            ));
-                        });
-                        skipped += 1;
-                        pb.inc(1);
-                        continue;
-                    }
+    if !manifest_blob_path.exists() {
+        let spinner = create_spinner("Downloading manifest...");
+        download_manifest(client, server, token, project_id, manifest_hash).await?;
+        spinner.finish_and_clear();
+    }
 
-                    restored += 1;
-                }
-                Err(_) => {
-                    pb.suspend(|| {
-                        print_warn(&format!("Wrong passphrase for '{}', skipping", file_path));
-                    });
-                    skipped += 1;
-                }
-            }
+    set_manifest(manifest_hash)?;
 
-            pb.inc(1);
-        }
+    let manifest = load_manifest()?;
+    let files = select_files(&manifest, profile);
+    if !files.is_empty() {
+        download_files(client, server, token, project_id, &files).await?;
+        restore_files(&files, project_id, profile).await?;
+    } else if let Some(profile) = profile {
+        print_info(&format!(
+            "No tracked files belong to environment '{}'.",
+            profile.name
+        ));
+    }
 
-        pb.finish_and_clear();
+    Ok(())
+}
 
-        if restored > 0 {
-            print_success(&format!("Restored {} file(s).", restored));
-        }
-        if skipped > 0 {
-            print_info(&format!("Skipped {} file(s).", skipped));
-        }
+/// Refuses a remote HEAD that is not a descendant of the last one we accepted,
+/// since an untrusted server could otherwise roll us back to revoked secrets.
+fn verify_remote_advance(
+    previous: &str,
+    remote_head: &str,
+    allow_rewind: bool,
+) -> anyhow::Result<()> {
+    if is_ancestor(previous, remote_head)? {
+        return Ok(());
     }
 
-    write_head(remote_head)?;
-    write_remote_head(remote_head)?;
-    write_applied(manifest_hash)?;
+    // Our old history may no longer decrypt (e.g. after a rekey), in which case
+    // this is reported as a fork rather than a rollback.
+    let is_rollback = read_accepted_heads().iter().any(|hash| hash == remote_head)
+        || is_known_ancestor(remote_head, previous);
+
+    let problem = if is_rollback {
+        format!(
+            "Remote HEAD {} is an older commit than origin/HEAD {} (possible rollback).",
+            short_hash(remote_head, 8),
+            short_hash(previous, 8)
+        )
+    } else {
+        format!(
+            "Remote HEAD {} does not descend from origin/HEAD {} (history was rewritten or forked).",
+            short_hash(remote_head, 8),
+            short_hash(previous, 8)
+        )
+    };
+
+    reject_rewind(&problem, allow_rewind)
+}
 
-    println!();
-    print_kv("HEAD", &remote_head[..12]);
-    print_success(&format!("Updated to commit {}.", &remote_head[..8]));
+fn reject_rewind(problem: &str, allow_rewind: bool) -> anyhow::Result<()> {
+    if allow_rewind {
+        print_warn(problem);
+        print_warn("Continuing because --allow-rewind was given.");
+        return Ok(());
+    }
 
-    Ok(())
+    bail!(
+        "{} Re-run with `--allow-rewind` if this is expected, for example after `envy rekey`.",
+        problem
+    )
 }
 
 /// Legacy pull for backwards compatibility
@@ -228,13 +326,15 @@ async fn legacy_pull(
     server: &str,
     token: &str,
     project_id: &str,
+    profile: Option<&Profile>,
 ) -> anyhow::Result<()> {
     let manifest_hash = tokio::fs::read_to_string(".envoy/latest")
         .await?
         .trim()
         .to_string();
 
-    if let Some(applied) = read_applied()
+    if profile.is_none()
+        && let Some(applied) = read_applied()
         && applied == manifest_hash
     {
         print_success("Already up to date.");
@@ -250,131 +350,209 @@ async fn legacy_pull(
     }
 
     let manifest = load_manifest()?;
-    if !manifest.files.is_empty() {
-        print_header(&format!("Pulling {} file(s)", manifest.files.len()));
+    let files = select_files(&manifest, profile);
+    if !files.is_empty() {
+        download_files(client, server, token, project_id, &files).await?;
+        restore_files(&files, project_id, profile).await?;
+    } else if let Some(profile) = profile {
+        print_info(&format!(
+            "No tracked files belong to environment '{}'.",
+            profile.name
+        ));
+    }
 
-        let pb = create_progress_bar(manifest.files.len() as u64);
+    write_applied(&manifest_hash)?;
 
-        let mut downloaded = 0;
+    println!();
+    print_kv("Manifest", short_hash(&manifest_hash, 12));
+    print_success(&format!(
+        "Updated to manifest {}.",
+        short_hash(&manifest_hash, 8)
+    ));
 
-        for hash in manifest.files.values() {
-            let path = Path::new(".envoy/cache").join(format!("{}.blob", hash));
+    Ok(())
+}
 
-            if path.exists() {
-                pb.inc(1);
-                continue;
-            }
+fn select_files<'a>(
+    manifest: &'a Manifest,
+    profile: Option<&Profile>,
+) -> Vec<(&'a String, &'a FileEntry)> {
+    match profile {
+        Some(profile) => profile.select(manifest),
+        None => manifest.files.iter().collect(),
+    }
+}
+
+async fn download_files(
+    client: &reqwest::Client,
+    server: &str,
+    token: &str,
+    project_id: &str,
+    files: &[(&String, &FileEntry)],
+) -> anyhow::Result<()> {
+    print_header(&format!("Pulling {} file(s)", files.len()));
+
+    let pb = create_progress_bar(files.len() as u64);
+    let mut downloaded = 0;
 
-            pb.set_message(format!("Downloading {}...", &hash[..8]));
-            download_blob(client, server, token, project_id, hash).await?;
+    for (_, entry) in files {
+        let hash = &entry.blob;
+        let path = Path::new(".envoy/cache").join(format!("{}.blob", hash));
 
-            downloaded += 1;
+        if path.exists() {
             pb.inc(1);
+            continue;
         }
 
-        pb.finish_and_clear();
+        pb.set_message(format!("Downloading {}...", short_hash(hash, 8)));
+        download_blob(client, server, token, project_id, hash).await?;
 
-        if downloaded > 0 {
-            print_success(&format!("Downloaded {} file(s).", downloaded));
-        }
+        downloaded += 1;
+        pb.inc(1);
+    }
 
-        let mut restored = 0;
-        let mut skipped = 0;
+    pb.finish_and_clear();
 
-        let pb = create_progress_bar(manifest.files.len() as u64);
-        pb.set_message("Restoring files...");
+    if downloaded > 0 {
+        print_success(&format!("Downloaded {} file(s).", downloaded));
+    }
 
-        for (file_path, hash) in &manifest.files {
-            let blob_path = Path::new(".envoy/cache").join(format!("{}.blob", hash));
-            let encrypted = tokio::fs::read(&blob_path)
-                .await
-                .map_err(|e| anyhow::anyhow!("Failed to read blob for '{}': {}", file_path, e))?;
+    Ok(())
+}
 
-            pb.suspend(|| {
-                println!();
-            });
+/// Decrypts `files` and writes them to the working tree, at the profile's
+/// restore targets when one is selected.
+async fn restore_files(
+    files: &[(&String, &FileEntry)],
+    project_id: &str,
+    profile: Option<&Profile>,
+) -> anyhow::Result<()> {
+    let manifest_key = get_project_key()?;
+    let profile_passphrase = profile.and_then(|profile| profile.file_passphrase());
+
+    let pb = create_progress_bar(files.len() as u64);
+    pb.set_message("Restoring files...");
+    let mut restored = 0;
+    let mut skipped = 0;
+
+    for (file_path, entry) in files {
+        let file_path = file_path.as_str();
+        let encrypted = read_blob(&entry.blob)
+            .map_err(|e| anyhow::anyhow!("Failed to read blob for '{}': {}", file_path, e))?;
+
+        let decrypted = if let Some(wrapped_key) = &entry.key {
+            decrypt_with_wrapped_key(
+                &encrypted,
+                wrapped_key,
+                &manifest_key,
+                project_id,
+                file_path,
+            )
+            .map_err(|_| format!("Failed to decrypt '{}'", file_path))
+        } else {
+            let passphrase = match &profile_passphrase {
+                Some(pass) => pass.clone(),
+                None => {
+                    pb.suspend(|| {
+                        println!();
+                    });
+
+                    match prompt_file_passphrase(file_path) {
+                        Ok(PassphraseResult::Passphrase(pass)) => pass,
+                        Ok(PassphraseResult::Skip) => {
+                            pb.suspend(|| {
+                                print_info(&format!("Skipping '{}'", file_path));
+                            });
+                            skipped += 1;
+                            pb.inc(1);
+                            continue;
+                        }
+                        Err(e) => {
+                            pb.suspend(|| {
+                                print_warn(&format!(
+                                    "Failed to read passphrase for '{}': {}",
+                                    file_path, e
+                                ));
+                            });
+                            skipped += 1;
+                            pb.inc(1);
+                            continue;
+                        }
+                    }
+                }
+            };
 
-            let passphrase = match prompt_file_passphrase(file_path) {
-                Ok(PassphraseResult::Passphrase(pass)) => pass,
-                Ok(PassphraseResult::Skip) => {
+            let aad = object_aad(project_id, ObjectKind::File, file_path);
+            decrypt_bytes(&encrypted, &passphrase, &aad)
+                .map_err(|_| format!("Wrong passphrase for '{}'", file_path))
+        };
+
+        match decrypted {
+            Ok(plaintext) => {
+                let target = match profile {
+                    Some(profile) => profile.target(file_path),
+                    None => normalize_path(file_path),
+                };
+                let target_path = to_native_path(&target);
+
+                if let Err(e) = ensure_parent_exists(&target_path) {
                     pb.suspend(|| {
-                        print_info(&format!("Skipping '{}'", file_path));
+                        print_warn(&format!(
+                            "Failed to create directory for '{}': {}",
+                            file_path, e
+                        ));
                     });
                     skipped += 1;
                     pb.inc(1);
                     continue;
                 }
-                Err(e) => {
+
+                if let Err(e) = tokio::fs::write(&target_path, plaintext).await {
                     pb.suspend(|| {
                         print_warn(&format!(
-                            "Failed to read passphrase for '{}': {}",
-                            file_path, e
+                            "Failed to write '{}': {}",
+                            target_path.display(),
+                            e
                         ));
                     });
                     skipped += 1;
                     pb.inc(1);
                     continue;
                 }
-            };
 
-            match decrypt_bytes(&encrypted, &passphrase) {
-                Ok(plaintext) => {
-                    let normalized = normalize_path(file_path);
-                    let target_path = to_native_path(&normalized);
-
-                    if let Err(e) = ensure_parent_exists(&target_path) {
-                        pb.suspend(|| {
-                            print_warn(&format!(
-                                "Failed to create directory for '{}': {}",
-                                file_path, e
-                            ));
-                        });
-                        skipped += 1;
-                        pb.inc(1);
-                        continue;
-                    }
-
-                    if let Err(e) = tokio::fs::write(&target_path, plaintext).await {
-                        pb.suspend(|| {
-                            print_warn(&format!(
-                                "Failed to write '{}': {}",
-                                target_path.display(),
-                                e
-                            ));
-                        });
-                        skipped += 1;
-                        pb.inc(1);
-                        continue;
-                    }
-
-                    restored += 1;
-                }
-                Err(_) => {
+                if let Some(mode) = entry.mode
+                    && let Err(e) = set_file_mode(&target_path, mode)
+                {
                     pb.suspend(|| {
-                        print_warn(&format!("Wrong passphrase for '{}', skipping", file_path));
+                        print_warn(&format!(
+                            "Failed to set permissions on '{}': {}",
+                            target_path.display(),
+                            e
+                        ));
                     });
-                    skipped += 1;
                 }
-            }
 
-            pb.inc(1);
+                restored += 1;
+            }
+            Err(message) => {
+                pb.suspend(|| {
+                    print_warn(&format!("{}, skipping", message));
+                });
+                skipped += 1;
+            }
         }
 
-        pb.finish_and_clear();
-
-        if restored > 0 {
-            print_success(&format!("Restored {} file(s).", restored));
-        }
-        if skipped > 0 {
-            print_info(&format!("Skipped {} file(s).", skipped));
-        }
+        pb.inc(1);
     }
 
-    write_applied(&manifest_hash)?;
+    pb.finish_and_clear();
 
-    println!();
-    print_kv("Manifest", &manifest_hash[..12]);
-    print_success(&format!("Updated to manifest {}.", &manifest_hash[..8]));
+    if restored > 0 {
+        print_success(&format!("Restored {} file(s).", restored));
+    }
+    if skipped > 0 {
+        print_info(&format!("Skipped {} file(s).", skipped));
+    }
 
     Ok(())
 }
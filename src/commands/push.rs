@@ -1,28 +1,33 @@
+use std::collections::BTreeSet;
 use std::path::Path;
 
+use crate::commands::{rekey::ensure_no_pending_rekey, tag::push_tags, validate::verify_before};
 use crate::utils::{
     commit::{
-        commit_blob_path, commits_ahead_of_remote, load_commit, read_head, read_remote_head,
-        write_remote_head,
+        commit_blob_path, commits_ahead_of_remote, ensure_attached, load_commit,
+        load_commit_manifest, read_head, read_remote_head, upstream_branch, write_remote_head,
     },
     config::load_token,
-    manifest::{load_manifest, save_manifest, write_applied},
+    manifest::{load_manifest, load_manifest_by_hash, save_manifest, write_applied},
     project_config::{get_remote_url, load_project_config},
     storage::{fetch_remote_head, update_remote_head, upload_blob, upload_commit, upload_manifest},
     ui::{
         create_progress_bar, print_error, print_header, print_info, print_kv, print_success,
-        print_warn,
+        print_warn, short_hash,
     },
 };
 use console::style;
 
-pub async fn push(remote: Option<&str>) -> anyhow::Result<()> {
+pub async fn push(remote: Option<&str>, no_verify: bool) -> anyhow::Result<()> {
+    ensure_attached("push")?;
+    ensure_no_pending_rekey("push")?;
     let token = load_token()?;
     let project = load_project_config()?;
     let server = get_remote_url(&project, remote)?;
 
     let manifest = load_manifest()?;
     let client = reqwest::Client::new();
+    let branch = upstream_branch();
 
     let local_head = read_head();
 
@@ -39,7 +44,7 @@ pub async fn push(remote: Option<&str>) -> anyhow::Result<()> {
     let local_head = local_head.unwrap();
 
     let remote_head_result =
-        fetch_remote_head(&client, &server, &token, &project.project_id).await?;
+        fetch_remote_head(&client, &server, &token, &project.project_id, &branch).await?;
 
     if let Some(ref server_head) = remote_head_result {
         let our_remote_head = read_remote_head();
@@ -55,26 +60,57 @@ pub async fn push(remote: Option<&str>) -> anyhow::Result<()> {
 
     let commits_to_push = commits_ahead_of_remote()?;
 
-    if commits_to_push.is_empty() {
-        print_success("Everything up to date.");
+    if read_remote_head().as_ref() == Some(&local_head) {
+        let tags = push_tags(&client, &server, &token, &project.project_id).await?;
+        if tags > 0 {
+            print_success(&format!("Pushed {} tag(s).", tags));
+        } else {
+            print_success("Everything up to date.");
+        }
         return Ok(());
     }
 
-    let total = manifest.files.len();
+    if !no_verify {
+        verify_before("push", &load_commit_manifest(&local_head)?)?;
+    }
+
+    let mut manifest_hashes = std::collections::HashSet::new();
+    for commit_hash in &commits_to_push {
+        let commit = load_commit(commit_hash)?;
+        manifest_hashes.insert(commit.manifest_hash);
+    }
+
+    // Every pushed commit's files are uploaded, not just the latest ones, so
+    // older versions can be restored on other machines.
+    let mut file_blobs: BTreeSet<String> = manifest
+        .files
+        .values()
+        .map(|entry| entry.blob.clone())
+        .collect();
+    for manifest_hash in &manifest_hashes {
+        file_blobs.extend(
+            load_manifest_by_hash(manifest_hash)?
+                .files
+                .into_values()
+                .map(|entry| entry.blob),
+        );
+    }
+
+    let total = file_blobs.len();
     let mut uploaded = 0;
     if total > 0 {
         print_header(&format!("Pushing {} file(s)", total));
 
         let pb = create_progress_bar(total as u64);
 
-        for hash in manifest.files.values() {
+        for hash in &file_blobs {
             let blob_path = Path::new(".envoy/cache").join(format!("{}.blob", hash));
 
             if !blob_path.exists() {
                 anyhow::bail!("Missing blob {}", hash);
             }
 
-            pb.set_message(format!("Uploading {}...", &hash[..8]));
+            pb.set_message(format!("Uploading {}...", short_hash(hash, 8)));
             upload_blob(
                 &client,
                 &server,
@@ -92,18 +128,15 @@ pub async fn push(remote: Option<&str>) -> anyhow::Result<()> {
         pb.finish_and_clear();
     }
 
-    let mut manifest_hashes = std::collections::HashSet::new();
-    for commit_hash in &commits_to_push {
-        let commit = load_commit(commit_hash)?;
-        manifest_hashes.insert(commit.manifest_hash);
-    }
-
     print_header(&format!("Pushing {} commit(s)", commits_to_push.len()));
     let total_uploads = commits_to_push.len() + manifest_hashes.len() + 1; // commits + manifests + HEAD update
     let pb = create_progress_bar(total_uploads as u64);
 
     for manifest_hash in &manifest_hashes {
-        pb.set_message(format!("Uploading manifest {}...", &manifest_hash[..8]));
+        pb.set_message(format!(
+            "Uploading manifest {}...",
+            short_hash(manifest_hash, 8)
+        ));
         let manifest_blob_path = Path::new(".envoy/cache").join(format!("{}.blob", manifest_hash));
         upload_manifest(
             &client,
@@ -118,7 +151,10 @@ pub async fn push(remote: Option<&str>) -> anyhow::Result<()> {
     }
 
     for commit_hash in commits_to_push.iter().rev() {
-        pb.set_message(format!("Uploading commit {}...", &commit_hash[..8]));
+        pb.set_message(format!(
+            "Uploading commit {}...",
+            short_hash(commit_hash, 8)
+        ));
         let commit_path = commit_blob_path(commit_hash);
 
         upload_commit(
@@ -140,6 +176,7 @@ pub async fn push(remote: Option<&str>) -> anyhow::Result<()> {
         &server,
         &token,
         &project.project_id,
+        &branch,
         &local_head,
         expected_head.as_deref(),
     )
@@ -166,7 +203,12 @@ pub async fn push(remote: Option<&str>) -> anyhow::Result<()> {
         print_success(&format!("Uploaded {} file(s).", uploaded));
     }
     print_success(&format!("Pushed {} commit(s).", commits_to_push.len()));
-    print_kv("HEAD", &local_head[..12]);
+    let tags = push_tags(&client, &server, &token, &project.project_id).await?;
+    if tags > 0 {
+        print_success(&format!("Pushed {} tag(s).", tags));
+    }
+    print_kv("Branch", &branch);
+    print_kv("HEAD", short_hash(&local_head, 12));
 
     Ok(())
 }
@@ -186,14 +228,14 @@ async fn legacy_push(
 
         let pb = create_progress_bar(total as u64);
 
-        for hash in manifest.files.values() {
+        for hash in manifest.files.values().map(|entry| &entry.blob) {
             let blob_path = Path::new(".envoy/cache").join(format!("{}.blob", hash));
 
             if !blob_path.exists() {
                 anyhow::bail!("Missing blob {}", hash);
             }
 
-            pb.set_message(format!("Uploading {}...", &hash[..8]));
+            pb.set_message(format!("Uploading {}...", short_hash(hash, 8)));
             upload_blob(client, server, token, project_id, hash, &blob_path).await?;
 
             uploaded += 1;
@@ -227,7 +269,7 @@ async fn legacy_push(
         print_success(&format!("Uploaded {} file(s).", uploaded));
     }
     print_success("Manifest saved.");
-    print_kv("Manifest", &manifest_hash[..12]);
+    print_kv("Manifest", short_hash(&manifest_hash, 12));
 
     Ok(())
 }
@@ -0,0 +1,784 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use anyhow::bail;
+use console::style;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::{
+    commands::{
+        crypto::{ObjectKind, decrypt_bytes_with_key, object_aad, rotate_data_key},
+        tag::fetch_tags,
+    },
+    utils::{
+        commit::{
+            Commit, commit_blob_path, commits_ahead_of_remote, ensure_attached, list_branches,
+            load_commit, read_head, read_remote_head, save_commit_with_key, upstream_branch,
+            walk_history, write_head, write_remote_head,
+        },
+        config::load_token,
+        manifest::{
+            FileEntry, get_current_manifest_hash, load_manifest_by_hash, read_applied,
+            save_manifest_with_key, set_manifest, write_applied,
+        },
+        project_config::{get_remote_url, load_project_config},
+        session::{derive_manifest_key_from_passphrase, save_session, take_passphrase_override},
+        storage::{
+            download_blob, download_tag_object, fetch_remote_head, update_remote_head, upload_blob,
+            upload_commit, upload_manifest, upload_tag_object,
+        },
+        tag::{
+            TAG_INDEX_REF, Tag, TagIndex, list_tags, load_tag, load_tag_index_with_key,
+            load_tag_with_key, read_remote_tags, save_tag_index_with_key, save_tag_with_key,
+            tag_blob_path, tag_object_exists, write_remote_tags, write_tag_ref,
+        },
+        ui::{
+            create_progress_bar, create_spinner, print_header, print_info, print_kv, print_success,
+            print_warn, prompt_passphrase, short_hash,
+        },
+    },
+};
+
+struct Rekeyer<'a> {
+    project_id: &'a str,
+    old_key: &'a [u8],
+    new_key: &'a [u8],
+    manifests: HashMap<String, String>,
+    /// Re-encrypted entries by path, old blob and old wrapped key.
+    entries: HashMap<String, (String, String, String)>,
+    old_blobs: HashSet<String>,
+}
+
+impl Rekeyer<'_> {
+    fn manifest(&mut self, old_hash: &str) -> anyhow::Result<String> {
+        if let Some(new_hash) = self.manifests.get(old_hash) {
+            return Ok(new_hash.clone());
+        }
+
+        let mut manifest = load_manifest_by_hash(old_hash)?;
+
+        for (path, entry) in manifest.files.iter_mut() {
+            self.entry(path, entry)?;
+        }
+
+        let new_hash = save_manifest_with_key(&manifest, self.new_key)?;
+        self.manifests
+            .insert(old_hash.to_string(), new_hash.clone());
+
+        Ok(new_hash)
+    }
+
+    /// Re-encrypts the entry's file under a fresh data key wrapped with the
+    /// new key, and recomputes its digest.
+    fn entry(&mut self, path: &str, entry: &mut FileEntry) -> anyhow::Result<()> {
+        let Some(wrapped) = entry.key.clone() else {
+            // Passphrase-encrypted files cannot be decrypted here, so their
+            // digests are dropped until the file is next encrypted.
+            entry.digest = None;
+            return Ok(());
+        };
+
+        let cache_key = format!("{}:{}:{}", path, entry.blob, wrapped);
+        let (blob, rewrapped, digest) = match self.entries.get(&cache_key) {
+            Some(rotated) => rotated.clone(),
+            None => {
+                let rotated = rotate_data_key(
+                    &entry.blob,
+                    &wrapped,
+                    self.old_key,
+                    self.new_key,
+                    self.project_id,
+                    path,
+                )
+                .map_err(|e| anyhow::anyhow!("Failed to re-encrypt '{}': {}", path, e))?;
+                let rotated = (rotated.blob, rotated.wrapped_key, rotated.digest);
+
+                self.old_blobs.insert(entry.blob.clone());
+                self.entries.insert(cache_key, rotated.clone());
+                rotated
+            }
+        };
+
+        entry.blob = blob;
+        entry.key = Some(rewrapped);
+        entry.digest = Some(digest);
+
+        Ok(())
+    }
+
+    /// Blobs written for re-encrypted files.
+    fn new_blobs(&self) -> impl Iterator<Item = &String> {
+        self.entries.values().map(|(blob, _, _)| blob)
+    }
+}
+
+fn can_decrypt(
+    blob_path: &Path,
+    kind: ObjectKind,
+    role: &str,
+    manifest_key: &[u8],
+    project_id: &str,
+) -> anyhow::Result<bool> {
+    let encrypted = fs::read(blob_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", blob_path.display(), e))?;
+
+    Ok(decrypt_bytes_with_key(
+        &encrypted,
+        manifest_key,
+        &object_aad(project_id, kind, role),
+    )
+    .is_ok())
+}
+
+fn verify_project_key(manifest_key: &[u8], project_id: &str) -> anyhow::Result<()> {
+    let (blob_path, kind) = match (get_current_manifest_hash(), read_head()) {
+        (Some(manifest_hash), _) => (
+            Path::new(".envoy/cache").join(format!("{}.blob", manifest_hash)),
+            ObjectKind::Manifest,
+        ),
+        (None, Some(head)) => (commit_blob_path(&head), ObjectKind::Commit),
+        (None, None) => return Ok(()),
+    };
+
+    if !can_decrypt(&blob_path, kind, "", manifest_key, project_id)? {
+        bail!("Current passphrase is incorrect.");
+    }
+
+    Ok(())
+}
+
+const PENDING_REKEY_PATH: &str = ".envoy/REKEY";
+
+/// The ref updates of a rekey whose objects are already uploaded. It is saved
+/// before any remote ref moves, so an interrupted rekey is finished by running
+/// `envy rekey` again instead of leaving HEAD and the session out of step.
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingRekey {
+    branch: String,
+    /// Remote HEAD of the branch when the rekey started.
+    old_head: Option<String>,
+    new_head: Option<String>,
+    /// Remote tag index when the rekey started.
+    old_tags: Option<String>,
+    new_tags: Option<String>,
+    /// Local tag refs, by name.
+    tags: Vec<(String, String)>,
+    staged_manifest: Option<String>,
+    applied_manifest: Option<String>,
+    /// Rewritten commits, old hash first.
+    commits: Vec<(String, String)>,
+    /// Local objects only readable with the old key, removed once finished.
+    stale_tags: Vec<String>,
+    stale_blobs: Vec<String>,
+}
+
+impl PendingRekey {
+    /// Checks `manifest_key` against an object the rekey wrote.
+    fn verify_key(&self, manifest_key: &[u8], project_id: &str) -> anyhow::Result<()> {
+        let (blob_path, kind, role) = match (&self.new_head, &self.staged_manifest, &self.new_tags)
+        {
+            (Some(head), _, _) => (commit_blob_path(head), ObjectKind::Commit, ""),
+            (None, Some(manifest), _) => (
+                Path::new(".envoy/cache").join(format!("{}.blob", manifest)),
+                ObjectKind::Manifest,
+                "",
+            ),
+            (None, None, Some(index)) => (tag_blob_path(index), ObjectKind::Tag, "index"),
+            (None, None, None) => return Ok(()),
+        };
+
+        if !can_decrypt(&blob_path, kind, role, manifest_key, project_id)? {
+            bail!("The new passphrase does not match the one the interrupted rekey used.");
+        }
+
+        Ok(())
+    }
+}
+
+/// Refuses to `action` while a rekey is half done, since HEAD is about to be
+/// replaced by the rewritten history.
+pub fn ensure_no_pending_rekey(action: &str) -> anyhow::Result<()> {
+    if Path::new(PENDING_REKEY_PATH).exists() {
+        bail!(
+            "A rekey was interrupted. Run `envy rekey` with the new passphrase to finish it before you {}.",
+            action
+        );
+    }
+    Ok(())
+}
+
+fn load_pending_rekey() -> anyhow::Result<Option<PendingRekey>> {
+    match fs::read(PENDING_REKEY_PATH) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", PENDING_REKEY_PATH, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(anyhow::anyhow!(
+            "Failed to read {}: {}",
+            PENDING_REKEY_PATH,
+            e
+        )),
+    }
+}
+
+fn save_pending_rekey(pending: &PendingRekey) -> anyhow::Result<()> {
+    let json = serde_json::to_vec_pretty(pending)?;
+    fs::write(PENDING_REKEY_PATH, json)
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", PENDING_REKEY_PATH, e))
+}
+
+/// Moves the remote refs to the rewritten objects, then the local refs and the
+/// session. Remote steps a previous run already completed are skipped.
+async fn finish_rekey(
+    pending: &PendingRekey,
+    old_key: Option<&[u8]>,
+    new_key: &[u8],
+    client: &reqwest::Client,
+    server: &str,
+    token: &str,
+    project_id: &str,
+) -> anyhow::Result<()> {
+    let spinner = create_spinner("Updating remote refs...");
+
+    if let Some(new_head) = &pending.new_head {
+        let current = fetch_remote_head(client, server, token, project_id, &pending.branch).await?;
+        if current.as_ref() != Some(new_head)
+            && let Err(e) = update_remote_head(
+                client,
+                server,
+                token,
+                project_id,
+                &pending.branch,
+                new_head,
+                pending.old_head.as_deref(),
+            )
+            .await
+        {
+            let current =
+                fetch_remote_head(client, server, token, project_id, &pending.branch).await;
+            if current.ok().flatten().as_ref() != Some(new_head) {
+                // Nothing was published, so the rekey is dropped rather than retried.
+                fs::remove_file(PENDING_REKEY_PATH)?;
+                bail!(
+                    "{}. Nothing was changed; run `envy pull`, then rekey again.",
+                    e
+                );
+            }
+        }
+    }
+
+    // Tags published under the old key while the rekey ran are carried over
+    // into the rewritten index instead of being overwritten.
+    let mut tags = pending.tags.clone();
+    let mut published_tags = pending.new_tags.clone();
+    if let Some(new_tags) = &pending.new_tags {
+        let mut index_hash = new_tags.clone();
+        let mut expected = pending.old_tags.clone();
+        let mut attempts = 0;
+        loop {
+            let current =
+                fetch_remote_head(client, server, token, project_id, TAG_INDEX_REF).await?;
+            if current.as_ref() == Some(&index_hash) {
+                break;
+            }
+            if current != expected {
+                let Some(current_hash) = current.clone() else {
+                    bail!("The remote tag index was removed while the rekey ran.");
+                };
+                if !tag_object_exists(&current_hash) {
+                    download_tag_object(client, server, token, project_id, &current_hash).await?;
+                }
+
+                // An earlier run of this rekey already published a merged index.
+                if let Ok(index) = load_tag_index_with_key(&current_hash, new_key) {
+                    tags = index.tags.into_iter().collect();
+                    index_hash = current_hash;
+                    break;
+                }
+
+                let Some(old_key) = old_key else {
+                    bail!(
+                        "Tags were published while the rekey was interrupted. Run `envy rekey -p <old passphrase>` with the new passphrase to carry them over."
+                    );
+                };
+                let remote = load_tag_index_with_key(&current_hash, old_key)?;
+                let mut index = load_tag_index_with_key(&index_hash, new_key)?;
+
+                for (name, hash) in remote.tags {
+                    if index.tags.contains_key(&name) {
+                        continue;
+                    }
+                    if !tag_object_exists(&hash) {
+                        download_tag_object(client, server, token, project_id, &hash).await?;
+                    }
+                    let tag = load_tag_with_key(&hash, old_key)?;
+                    let Some((_, commit)) =
+                        pending.commits.iter().find(|(old, _)| *old == tag.commit)
+                    else {
+                        print_warn(&format!(
+                            "Tag '{}' points to a commit outside the rekeyed history; dropping it.",
+                            name
+                        ));
+                        continue;
+                    };
+
+                    let new_hash = save_tag_with_key(
+                        &Tag {
+                            commit: commit.clone(),
+                            ..tag
+                        },
+                        new_key,
+                    )?;
+                    upload_tag_object(
+                        client,
+                        server,
+                        token,
+                        project_id,
+                        &new_hash,
+                        &tag_blob_path(&new_hash),
+                    )
+                    .await?;
+                    let _ = fs::remove_file(tag_blob_path(&hash));
+                    index.tags.insert(name.clone(), new_hash.clone());
+                    tags.push((name, new_hash));
+                }
+
+                index_hash = save_tag_index_with_key(&index, new_key)?;
+                upload_tag_object(
+                    client,
+                    server,
+                    token,
+                    project_id,
+                    &index_hash,
+                    &tag_blob_path(&index_hash),
+                )
+                .await?;
+                let _ = fs::remove_file(tag_blob_path(&current_hash));
+                expected = current;
+            }
+
+            attempts += 1;
+            match update_remote_head(
+                client,
+                server,
+                token,
+                project_id,
+                TAG_INDEX_REF,
+                &index_hash,
+                expected.as_deref(),
+            )
+            .await
+            {
+                Ok(()) => break,
+                Err(e) if attempts >= 3 => {
+                    bail!(
+                        "Failed to update remote tags: {}. Run `envy rekey` again to finish.",
+                        e
+                    );
+                }
+                Err(_) => {}
+            }
+        }
+        published_tags = Some(index_hash);
+    }
+
+    spinner.finish_and_clear();
+
+    if let Some(new_head) = &pending.new_head {
+        write_head(new_head)?;
+        write_remote_head(new_head)?;
+    }
+    for (name, hash) in &tags {
+        write_tag_ref(name, hash)?;
+    }
+    if let Some(index) = &published_tags {
+        write_remote_tags(index)?;
+    }
+    if let Some(hash) = &pending.staged_manifest {
+        set_manifest(hash)?;
+    }
+    if let Some(hash) = &pending.applied_manifest {
+        write_applied(hash)?;
+    }
+    save_session(project_id, new_key)?;
+    fs::remove_file(PENDING_REKEY_PATH)?;
+
+    // Objects encrypted under the old key can no longer be read with the new session.
+    for (hash, _) in &pending.commits {
+        let _ = fs::remove_file(commit_blob_path(hash));
+    }
+    for hash in &pending.stale_tags {
+        let _ = fs::remove_file(tag_blob_path(hash));
+    }
+    for hash in &pending.stale_blobs {
+        let _ = fs::remove_file(Path::new(".envoy/cache").join(format!("{}.blob", hash)));
+    }
+
+    Ok(())
+}
+
+fn prompt_new_passphrase() -> anyhow::Result<String> {
+    let passphrase = prompt_passphrase("New project passphrase", 6)?;
+    let confirmation = prompt_passphrase("Confirm new passphrase", 6)?;
+
+    if passphrase != confirmation {
+        bail!("Passphrases do not match.");
+    }
+
+    Ok(passphrase)
+}
+
+pub async fn rekey(remote: Option<&str>, new_passphrase: Option<String>) -> anyhow::Result<()> {
+    ensure_attached("rekey")?;
+    let branches = list_branches();
+    if branches.len() > 1 {
+        bail!(
+            "Rekey rewrites the history of one branch, but there are {}. Delete the others with `envy branch -d` first.",
+            branches.len()
+        );
+    }
+    let branch = upstream_branch();
+    let token = load_token()?;
+    let project = load_project_config()?;
+    let server = get_remote_url(&project, remote)?;
+    let client = reqwest::Client::new();
+
+    if let Some(pending) = load_pending_rekey()? {
+        print_info("Finishing an interrupted rekey.");
+        let new_passphrase = match new_passphrase {
+            Some(pass) => pass,
+            None => prompt_passphrase("New project passphrase", 6)?,
+        };
+        let mut new_key =
+            derive_manifest_key_from_passphrase(&new_passphrase, &project.project_id)?;
+        // Only needed to carry over tags published while the rekey was interrupted.
+        let mut old_key = take_passphrase_override()
+            .map(|pass| derive_manifest_key_from_passphrase(&pass, &project.project_id))
+            .transpose()?;
+
+        let result = match pending.verify_key(&new_key, &project.project_id) {
+            Ok(()) => {
+                finish_rekey(
+                    &pending,
+                    old_key.as_deref(),
+                    &new_key,
+                    &client,
+                    &server,
+                    &token,
+                    &project.project_id,
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        old_key.zeroize();
+        new_key.zeroize();
+        result?;
+
+        println!();
+        print_rekey_done();
+        return Ok(());
+    }
+
+    let old_passphrase = match take_passphrase_override() {
+        Some(pass) => pass,
+        None => prompt_passphrase("Current project passphrase", 6)?,
+    };
+    let mut old_key = derive_manifest_key_from_passphrase(&old_passphrase, &project.project_id)?;
+    verify_project_key(&old_key, &project.project_id)?;
+    save_session(&project.project_id, &old_key)?;
+
+    let new_passphrase = match new_passphrase {
+        Some(pass) if pass.len() < 6 => bail!("Passphrase must be at least 6 characters long"),
+        Some(pass) => pass,
+        None => prompt_new_passphrase()?,
+    };
+
+    if new_passphrase == old_passphrase {
+        bail!("New passphrase must differ from the current one.");
+    }
+
+    let mut new_key = derive_manifest_key_from_passphrase(&new_passphrase, &project.project_id)?;
+
+    let server_head =
+        fetch_remote_head(&client, &server, &token, &project.project_id, &branch).await?;
+    if server_head.is_some() && server_head != read_remote_head() {
+        bail!("Remote has new commits. Run `envy pull` first, then rekey.");
+    }
+    fetch_tags(&client, &server, &token, &project.project_id).await?;
+
+    let local_head = read_head();
+    let history = match &local_head {
+        Some(head) => walk_history(head, None)?,
+        None => Vec::new(),
+    };
+
+    // Passphrase-encrypted files are kept as they are, so only those of
+    // unpushed commits still need uploading.
+    let mut file_blobs = HashSet::new();
+    for commit_hash in commits_ahead_of_remote()? {
+        let commit = load_commit(&commit_hash)?;
+        file_blobs.extend(
+            load_manifest_by_hash(&commit.manifest_hash)?
+                .files
+                .into_values()
+                .filter(|entry| entry.key.is_none())
+                .map(|entry| entry.blob),
+        );
+    }
+
+    let staged_hash = get_current_manifest_hash().filter(|hash| {
+        Path::new(".envoy/cache")
+            .join(format!("{}.blob", hash))
+            .exists()
+    });
+
+    // Every project-key file in history is re-encrypted, so blobs that were
+    // never restored here are fetched first.
+    let mut missing = BTreeSet::new();
+    for manifest_hash in history
+        .iter()
+        .map(|(_, commit)| &commit.manifest_hash)
+        .chain(staged_hash.as_ref())
+    {
+        missing.extend(
+            load_manifest_by_hash(manifest_hash)?
+                .files
+                .into_values()
+                .filter(|entry| entry.key.is_some())
+                .map(|entry| entry.blob)
+                .filter(|blob| {
+                    !Path::new(".envoy/cache")
+                        .join(format!("{}.blob", blob))
+                        .exists()
+                }),
+        );
+    }
+    if !missing.is_empty() {
+        let spinner = create_spinner(&format!("Fetching {} file(s)...", missing.len()));
+        for hash in &missing {
+            download_blob(&client, &server, &token, &project.project_id, hash).await?;
+        }
+        spinner.finish_and_clear();
+    }
+
+    let spinner = create_spinner("Re-encrypting history...");
+
+    let mut rekeyer = Rekeyer {
+        project_id: &project.project_id,
+        old_key: &old_key,
+        new_key: &new_key,
+        manifests: HashMap::new(),
+        entries: HashMap::new(),
+        old_blobs: HashSet::new(),
+    };
+    let mut commit_map: HashMap<String, String> = HashMap::new();
+    let mut rewritten_commits: Vec<(String, String)> = Vec::new();
+
+    for (old_hash, commit) in history.iter().rev() {
+        let parent = match &commit.parent {
+            Some(parent) => Some(commit_map.get(parent).cloned().ok_or_else(|| {
+                anyhow::anyhow!("Commit {} is missing a parent", short_hash(old_hash, 8))
+            })?),
+            None => None,
+        };
+
+        let rewritten = Commit {
+            parent,
+            manifest_hash: rekeyer.manifest(&commit.manifest_hash)?,
+            ..commit.clone()
+        };
+
+        let new_hash = save_commit_with_key(&rewritten, &new_key)?;
+        commit_map.insert(old_hash.clone(), new_hash.clone());
+        rewritten_commits.push((old_hash.clone(), new_hash));
+    }
+
+    // Tags name commits by hash, so they are rewritten to the new commits.
+    let mut rewritten_tags: Vec<(String, String, String)> = Vec::new();
+    for (name, old_hash) in list_tags() {
+        let tag = load_tag(&old_hash)?;
+        let commit = commit_map.get(&tag.commit).cloned().ok_or_else(|| {
+            anyhow::anyhow!(
+                "Tag '{}' points to commit {}, which is not in the history being rekeyed",
+                name,
+                short_hash(&tag.commit, 8)
+            )
+        })?;
+        let new_hash = save_tag_with_key(&Tag { commit, ..tag }, &new_key)?;
+        rewritten_tags.push((name, old_hash, new_hash));
+    }
+
+    let commit_manifests: Vec<String> = rekeyer.manifests.values().cloned().collect();
+
+    let staged_manifest = match &staged_hash {
+        Some(hash) => Some(rekeyer.manifest(hash)?),
+        None => None,
+    };
+    file_blobs.extend(rekeyer.new_blobs().cloned());
+
+    let applied_manifest = read_applied().and_then(|hash| rekeyer.manifests.get(&hash).cloned());
+
+    // An index of tags published under the old key is replaced even when it
+    // is empty, since it could no longer be read.
+    let old_tags = read_remote_tags();
+    let new_tags = if rewritten_tags.is_empty() && old_tags.is_none() {
+        None
+    } else {
+        let mut index = TagIndex::default();
+        for (name, _, new_hash) in &rewritten_tags {
+            index.tags.insert(name.clone(), new_hash.clone());
+        }
+        Some(save_tag_index_with_key(&index, &new_key)?)
+    };
+
+    spinner.finish_and_clear();
+
+    let new_head = rewritten_commits.last().map(|(_, new)| new.clone());
+
+    if new_head.is_some() {
+        print_header(&format!(
+            "Pushing {} rewritten commit(s)",
+            rewritten_commits.len()
+        ));
+
+        let total = file_blobs.len() + commit_manifests.len() + rewritten_commits.len();
+        let pb = create_progress_bar(total as u64);
+
+        for hash in &file_blobs {
+            pb.set_message(format!("Uploading {}...", short_hash(hash, 8)));
+            let blob_path = Path::new(".envoy/cache").join(format!("{}.blob", hash));
+            upload_blob(
+                &client,
+                &server,
+                &token,
+                &project.project_id,
+                hash,
+                &blob_path,
+            )
+            .await?;
+            pb.inc(1);
+        }
+
+        for manifest_hash in &commit_manifests {
+            pb.set_message(format!(
+                "Uploading manifest {}...",
+                short_hash(manifest_hash, 8)
+            ));
+            let manifest_path = Path::new(".envoy/cache").join(format!("{}.blob", manifest_hash));
+            upload_manifest(
+                &client,
+                &server,
+                &token,
+                &project.project_id,
+                manifest_hash,
+                &manifest_path,
+            )
+            .await?;
+            pb.inc(1);
+        }
+
+        for (_, commit_hash) in &rewritten_commits {
+            pb.set_message(format!(
+                "Uploading commit {}...",
+                short_hash(commit_hash, 8)
+            ));
+            upload_commit(
+                &client,
+                &server,
+                &token,
+                &project.project_id,
+                commit_hash,
+                &commit_blob_path(commit_hash),
+            )
+            .await?;
+            pb.inc(1);
+        }
+
+        pb.finish_and_clear();
+    }
+
+    if let Some(index_hash) = &new_tags {
+        let spinner = create_spinner("Uploading rewritten tags...");
+        for hash in rewritten_tags
+            .iter()
+            .map(|(_, _, new_hash)| new_hash)
+            .chain(std::iter::once(index_hash))
+        {
+            upload_tag_object(
+                &client,
+                &server,
+                &token,
+                &project.project_id,
+                hash,
+                &tag_blob_path(hash),
+            )
+            .await?;
+        }
+        spinner.finish_and_clear();
+    }
+
+    let pending = PendingRekey {
+        branch,
+        old_head: server_head,
+        new_head: new_head.clone(),
+        old_tags: old_tags.clone(),
+        new_tags,
+        tags: rewritten_tags
+            .iter()
+            .map(|(name, _, new_hash)| (name.clone(), new_hash.clone()))
+            .collect(),
+        staged_manifest,
+        applied_manifest,
+        commits: rewritten_commits.clone(),
+        stale_tags: rewritten_tags
+            .iter()
+            .map(|(_, old_hash, _)| old_hash.clone())
+            .chain(old_tags)
+            .collect(),
+        stale_blobs: rekeyer
+            .manifests
+            .keys()
+            .chain(&rekeyer.old_blobs)
+            .cloned()
+            .collect(),
+    };
+    save_pending_rekey(&pending)?;
+
+    let result = finish_rekey(
+        &pending,
+        Some(&old_key),
+        &new_key,
+        &client,
+        &server,
+        &token,
+        &project.project_id,
+    )
+    .await;
+    old_key.zeroize();
+    new_key.zeroize();
+    result?;
+
+    println!();
+    if let Some(new_head) = &new_head {
+        print_kv("Commits", &rewritten_commits.len().to_string());
+        print_kv("HEAD", short_hash(new_head, 12));
+    }
+    print_rekey_done();
+
+    Ok(())
+}
+
+fn print_rekey_done() {
+    print_success("Project passphrase changed.");
+    print_warn("Share the new passphrase with your team; the old one no longer works.");
+    print_info(&format!(
+        "Teammates must run {} with the new passphrase. Files encrypted with their own passphrase are unchanged.",
+        style("`envy pull`").cyan()
+    ));
+    print_warn(
+        "Files were re-encrypted with new data keys, but the old objects stay on the server. Anyone who had the old passphrase may have copies of the secrets; change the secret values themselves to revoke their access.",
+    );
+}
@@ -0,0 +1,175 @@
+use std::path::Path;
+
+use anyhow::bail;
+use console::style;
+use zeroize::Zeroize;
+
+use crate::{
+    commands::crypto::unlock_tracked_file,
+    utils::{
+        commit::{load_commit, resolve_commit},
+        config::load_token,
+        manifest::{Manifest, load_manifest, load_manifest_by_hash},
+        paths::{ensure_parent_exists, normalize_path, set_file_mode, to_native_path},
+        project_config::{get_remote_url, load_project_config},
+        storage::{download_blob, download_manifest},
+        ui::{print_success, print_warn},
+        worktree::{build_globset, is_glob},
+    },
+};
+
+/// Tracked paths of `manifest` selected by `patterns`, which may be globs.
+pub fn select_paths(manifest: &Manifest, patterns: &[String]) -> anyhow::Result<Vec<String>> {
+    let mut selected = Vec::new();
+
+    for pattern in patterns {
+        let normalized = normalize_path(pattern);
+
+        if is_glob(&normalized) {
+            let set = build_globset(std::slice::from_ref(&normalized))?;
+            let before = selected.len();
+            selected.extend(
+                manifest
+                    .files
+                    .keys()
+                    .filter(|path| set.is_match(path.as_str()))
+                    .cloned(),
+            );
+            if selected.len() == before {
+                bail!("No tracked files match '{}'", pattern);
+            }
+        } else if manifest.files.contains_key(&normalized) {
+            selected.push(normalized);
+        } else {
+            bail!("'{}' is not tracked in that version", normalized);
+        }
+    }
+
+    selected.sort();
+    selected.dedup();
+    Ok(selected)
+}
+
+struct Remote {
+    client: reqwest::Client,
+    server: String,
+    token: String,
+    project_id: String,
+}
+
+impl Remote {
+    fn connect() -> anyhow::Result<Self> {
+        let project = load_project_config()?;
+        Ok(Self {
+            client: reqwest::Client::new(),
+            server: get_remote_url(&project, None)?,
+            token: load_token()?,
+            project_id: project.project_id,
+        })
+    }
+}
+
+fn is_cached(hash: &str) -> bool {
+    Path::new(".envoy/cache")
+        .join(format!("{}.blob", hash))
+        .exists()
+}
+
+/// Loads the manifest of a commit, downloading it if only the commit is
+/// cached (pull fetches the manifest of the remote HEAD alone).
+pub async fn load_commit_manifest_fetching(rev: &str) -> anyhow::Result<Manifest> {
+    let commit = load_commit(&resolve_commit(rev)?)?;
+
+    if !is_cached(&commit.manifest_hash) {
+        let remote = Remote::connect()?;
+        download_manifest(
+            &remote.client,
+            &remote.server,
+            &remote.token,
+            &remote.project_id,
+            &commit.manifest_hash,
+        )
+        .await?;
+    }
+
+    load_manifest_by_hash(&commit.manifest_hash)
+}
+
+/// Downloads the blobs of `paths` that are not in the local cache, which is
+/// common for files from older commits.
+pub async fn fetch_missing_blobs(manifest: &Manifest, paths: &[String]) -> anyhow::Result<()> {
+    let missing: Vec<&str> = paths
+        .iter()
+        .filter_map(|path| manifest.files.get(path))
+        .map(|entry| entry.blob.as_str())
+        .filter(|hash| !is_cached(hash))
+        .collect();
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let remote = Remote::connect()?;
+    for hash in missing {
+        download_blob(
+            &remote.client,
+            &remote.server,
+            &remote.token,
+            &remote.project_id,
+            hash,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Decrypts a tracked file from `manifest` and writes it to its path in the
+/// working tree with its recorded permissions.
+pub fn write_tracked_file(manifest: &Manifest, path: &str) -> anyhow::Result<()> {
+    let (mut plaintext, _) = unlock_tracked_file(manifest, path)?;
+    let target = to_native_path(path);
+
+    let written = ensure_parent_exists(&target).and_then(|_| std::fs::write(&target, &plaintext));
+    plaintext.zeroize();
+    written.map_err(|e| anyhow::anyhow!("Failed to write '{}': {}", path, e))?;
+
+    if let Some(mode) = manifest.files.get(path).and_then(|entry| entry.mode)
+        && let Err(e) = set_file_mode(&target, mode)
+    {
+        print_warn(&format!("Failed to set permissions on '{}': {}", path, e));
+    }
+
+    Ok(())
+}
+
+/// Writes tracked files from the staged manifest or `source` to disk without
+/// touching HEAD, origin/HEAD or the applied marker.
+pub async fn restore(patterns: &[String], source: Option<&str>) -> anyhow::Result<()> {
+    let (manifest, label) = match source {
+        Some(rev) => (load_commit_manifest_fetching(rev).await?, rev.to_string()),
+        None => (load_manifest()?, "the staged manifest".to_string()),
+    };
+
+    let paths = select_paths(&manifest, patterns)?;
+    fetch_missing_blobs(&manifest, &paths).await?;
+
+    for path in &paths {
+        write_tracked_file(&manifest, path)?;
+        print_success(&format!("Restored '{}' from {}.", path, label));
+    }
+
+    if source.is_some() {
+        let flag = match manifest.files.get(&paths[0]) {
+            Some(entry) if entry.key.is_some() => " --project-key",
+            _ => "",
+        };
+        print_warn(&format!(
+            "The staged manifest is unchanged. Run {} and {} to commit the restored version.",
+            style(format!("`envy add{} {}`", flag, paths[0])).cyan(),
+            style("`envy commit`").cyan()
+        ));
+    }
+
+    Ok(())
+}
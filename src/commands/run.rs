@@ -0,0 +1,79 @@
+use std::process::Command;
+
+use anyhow::bail;
+use zeroize::Zeroize;
+
+use crate::{
+    commands::crypto::unlock_tracked_file_with,
+    utils::{
+        dotenv::parse_env, manifest::load_manifest, paths::normalize_path, profile::load_profile,
+        project_config::load_project_config,
+    },
+};
+
+pub fn run(files: &[String], command: &[String], env: Option<&str>) -> anyhow::Result<()> {
+    let manifest = load_manifest()?;
+    let profile = load_profile(&load_project_config()?, env)?;
+
+    let selected: Vec<String> = match (&profile, files.is_empty()) {
+        (Some(profile), true) => profile
+            .select(&manifest)
+            .into_iter()
+            .map(|(path, _)| path.clone())
+            .collect(),
+        (None, true) => manifest.files.keys().cloned().collect(),
+        (_, false) => files.iter().map(|file| normalize_path(file)).collect(),
+    };
+
+    if let Some(profile) = &profile {
+        if selected.is_empty() {
+            bail!("No tracked files belong to environment '{}'.", profile.name);
+        }
+        if let Some(path) = selected.iter().find(|path| !profile.includes(path)) {
+            bail!("'{}' is not part of environment '{}'.", path, profile.name);
+        }
+    }
+
+    if selected.is_empty() {
+        bail!("No tracked files. Run `envy encrypt -i .env` first.");
+    }
+
+    let passphrase = profile
+        .as_ref()
+        .and_then(|profile| profile.file_passphrase());
+    let mut vars = Vec::new();
+
+    for path in &selected {
+        let (mut plaintext, _) = unlock_tracked_file_with(&manifest, path, passphrase.clone())?;
+        let parsed = parse_env(&plaintext, path);
+        plaintext.zeroize();
+
+        vars.extend(parsed?);
+    }
+
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("No command given"))?;
+
+    let mut child = Command::new(program);
+    child.args(args).envs(vars);
+
+    // Replace the current process so signals and exit codes reach the child
+    // directly, which matters when envy is a container entrypoint.
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+
+        let error = child.exec();
+        bail!("Failed to run '{}': {}", program, error);
+    }
+
+    #[cfg(not(unix))]
+    {
+        let status = child
+            .status()
+            .map_err(|e| anyhow::anyhow!("Failed to run '{}': {}", program, e))?;
+
+        std::process::exit(status.code().unwrap_or(1));
+    }
+}
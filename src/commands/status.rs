@@ -1,22 +1,33 @@
 use crate::utils::{
-    commit::{commits_ahead_of_remote, get_head_manifest_hash, read_head, read_remote_head},
+    commit::{
+        DEFAULT_BRANCH, commits_ahead_of_remote, current_branch, get_head_manifest_hash,
+        read_detached, read_head, read_remote_head, upstream_branch,
+    },
     config::load_token,
     manifest::{
-        compute_manifest_content_hash, get_current_manifest_hash, load_manifest,
+        LazyProjectKey, compute_manifest_content_hash, get_current_manifest_hash, load_manifest,
         load_manifest_by_hash,
     },
+    profile::load_profile,
     project_config::{get_remote_url, load_project_config},
     storage::fetch_remote_head,
-    ui::{print_header, print_info, print_kv, print_success, print_warn},
+    ui::{
+        print_header, print_info, print_kv, print_kv_warn, print_success, print_warn, short_hash,
+    },
+    worktree::{FileStatus, TrackingRules, excluded_tracked, tracked_statuses, untracked_files},
 };
 use console::style;
 use std::path::Path;
 
-pub async fn status() -> anyhow::Result<()> {
+pub async fn status(env: Option<&str>) -> anyhow::Result<()> {
     let project = load_project_config()?;
+    let profile = load_profile(&project, env)?;
 
     print_header("Envoy Status");
     print_kv("Project", &project.project_id);
+    if let Some(profile) = &profile {
+        print_kv("Environment", &profile.name);
+    }
 
     let current_manifest_hash = get_current_manifest_hash();
     let manifest = load_manifest()?;
@@ -29,10 +40,16 @@ pub async fn status() -> anyhow::Result<()> {
         if let Ok(token) = load_token() {
             if let Ok(server) = get_remote_url(&project, None) {
                 let client = reqwest::Client::new();
-                fetch_remote_head(&client, &server, &token, &project.project_id)
-                    .await
-                    .ok()
-                    .flatten()
+                fetch_remote_head(
+                    &client,
+                    &server,
+                    &token,
+                    &project.project_id,
+                    &upstream_branch(),
+                )
+                .await
+                .ok()
+                .flatten()
             } else {
                 None
             }
@@ -42,19 +59,35 @@ pub async fn status() -> anyhow::Result<()> {
     };
 
     if let Some(ref hash) = current_manifest_hash {
-        print_kv("Manifest", &hash[..12]);
+        print_kv("Manifest", short_hash(hash, 12));
     }
 
     print_kv("Files", &manifest.files.len().to_string());
 
     println!();
 
+    if let Some(branch) = current_branch() {
+        print_kv("Branch", &branch);
+    }
+
     if let Some(ref head) = local_head {
-        print_kv("HEAD", &head[..12]);
+        match read_detached() {
+            Some(branch) => print_kv_warn(
+                "HEAD",
+                &format!("{} (detached from {})", short_hash(head, 12), branch),
+            ),
+            None => print_kv("HEAD", short_hash(head, 12)),
+        }
     }
 
     if let Some(ref remote) = local_remote_head {
-        print_kv("origin/HEAD", &remote[..12]);
+        let branch = upstream_branch();
+        let label = if branch == DEFAULT_BRANCH {
+            "origin/HEAD".to_string()
+        } else {
+            format!("origin/{}", branch)
+        };
+        print_kv(&label, short_hash(remote, 12));
     }
 
     let is_behind_remote =
@@ -63,27 +96,66 @@ pub async fn status() -> anyhow::Result<()> {
     let has_new_remote_commits =
         server_remote_head.is_some() && local_head.as_ref() != server_remote_head.as_ref();
 
-    let has_uncommitted_changes = {
-        let current_content_hash = compute_manifest_content_hash(&manifest);
-        match head_manifest_hash {
-            Some(ref head_hash) => match load_manifest_by_hash(head_hash) {
-                Ok(head_manifest) => {
-                    let head_content_hash = compute_manifest_content_hash(&head_manifest);
-                    current_content_hash != head_content_hash
-                }
-                Err(_) => true,
-            },
-            None => !manifest.files.is_empty(),
+    let head_manifest = head_manifest_hash
+        .as_ref()
+        .and_then(|hash| load_manifest_by_hash(hash).ok());
+
+    let has_uncommitted_changes = match (&head_manifest_hash, &head_manifest) {
+        (Some(_), Some(head_manifest)) => {
+            compute_manifest_content_hash(&manifest) != compute_manifest_content_hash(head_manifest)
         }
+        (Some(_), None) => true,
+        (None, _) => !manifest.files.is_empty(),
     };
 
+    let statuses = tracked_statuses(
+        &manifest,
+        head_manifest.as_ref(),
+        &mut LazyProjectKey::default(),
+        profile.as_ref(),
+    )?;
+
+    let rules = TrackingRules::load(&project)?;
+    let mut untracked = untracked_files(Path::new("."), &manifest, &rules);
+    if let Some(profile) = &profile {
+        untracked.retain(|path| profile.includes(path));
+    }
+    let excluded = excluded_tracked(&manifest, &rules);
+
+    let modified: Vec<&str> = statuses
+        .iter()
+        .filter(|(_, status)| matches!(status, FileStatus::Modified | FileStatus::Deleted))
+        .map(|(path, _)| path.as_str())
+        .collect();
+
+    if !statuses.is_empty() || !untracked.is_empty() {
+        println!();
+        for (path, status) in &statuses {
+            let label = format!("{:<12}", status.label());
+            if *status == FileStatus::Unchanged {
+                print_kv(&label, path);
+            } else {
+                print_kv_warn(&label, path);
+            }
+        }
+        for path in &untracked {
+            print_kv_warn(&format!("{:<12}", "untracked"), path);
+        }
+    }
+
     let commits_ahead = commits_ahead_of_remote().unwrap_or_default();
     let has_unpushed_commits = !commits_ahead.is_empty();
 
     let mut missing_blobs = 0;
-    for hash in manifest.files.values() {
-        let path = Path::new(".envoy/cache").join(format!("{}.blob", hash));
-        if !path.exists() {
+    for (path, entry) in &manifest.files {
+        if profile
+            .as_ref()
+            .is_some_and(|profile| !profile.includes(path))
+        {
+            continue;
+        }
+        let blob_path = Path::new(".envoy/cache").join(format!("{}.blob", entry.blob));
+        if !blob_path.exists() {
             missing_blobs += 1;
         }
     }
@@ -110,6 +182,14 @@ pub async fn status() -> anyhow::Result<()> {
             "Remote has new commits. Run {} to sync.",
             style("`envy pull`").cyan()
         ));
+    } else if !modified.is_empty() {
+        print_warn("State: MODIFIED ON DISK");
+        print_info(&format!(
+            "{} file(s) changed since they were encrypted. Run {} to re-encrypt, or {} to stop tracking.",
+            modified.len(),
+            style(format!("`envy encrypt -i {}`", modified[0])).cyan(),
+            style("`envy remove`").cyan()
+        ));
     } else if has_uncommitted_changes {
         print_warn("State: UNCOMMITTED CHANGES");
         if manifest.files.is_empty() && head_manifest_hash.is_some() {
@@ -144,5 +224,31 @@ pub async fn status() -> anyhow::Result<()> {
         print_success("State: UP TO DATE");
     }
 
+    if let Some((path, _)) = statuses
+        .iter()
+        .find(|(_, status)| *status == FileStatus::NotRestored)
+    {
+        print_info(&format!(
+            "Some committed files are missing from disk. Run {} to restore one.",
+            style(format!("`envy restore {}`", path)).cyan()
+        ));
+    }
+
+    if !untracked.is_empty() {
+        print_info(&format!(
+            "{} untracked env file(s). Run {} to track them.",
+            untracked.len(),
+            style("`envy add`").cyan()
+        ));
+    }
+
+    if let Some(path) = excluded.first() {
+        print_warn(&format!(
+            "{} tracked file(s) match exclude rules. Run {} to stop tracking one, or adjust `[tracking]`.",
+            excluded.len(),
+            style(format!("`envy remove -i {}`", path)).cyan()
+        ));
+    }
+
     Ok(())
 }
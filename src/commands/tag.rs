@@ -0,0 +1,242 @@
+use std::collections::BTreeMap;
+
+use anyhow::bail;
+use console::style;
+
+use crate::utils::{
+    commit::{is_known_ancestor, load_commit, read_remote_head, resolve_commit},
+    storage::{download_tag_object, fetch_remote_head, update_remote_head, upload_tag_object},
+    tag::{
+        TAG_INDEX_REF, Tag, TagIndex, list_tags, load_tag, load_tag_index, read_remote_tags,
+        read_tag_ref, save_tag, save_tag_index, tag_blob_path, tag_object_exists, try_load_tag,
+        validate_tag_name, write_remote_tags, write_tag_ref,
+    },
+    ui::{print_info, print_success, print_warn, short_hash},
+};
+
+pub fn list() -> anyhow::Result<()> {
+    let tags = list_tags();
+
+    if tags.is_empty() {
+        print_info("No tags yet.");
+        return Ok(());
+    }
+
+    for (name, hash) in &tags {
+        let tag = load_tag(hash)?;
+        println!(
+            "{} {} {}",
+            style(name).yellow().bold(),
+            style(short_hash(&tag.commit, 8)).dim(),
+            tag.message
+        );
+    }
+
+    Ok(())
+}
+
+/// Creates an annotated tag. Tags are never moved, so a published name always
+/// refers to the same secrets.
+pub fn create(
+    name: &str,
+    rev: Option<&str>,
+    message: Option<&str>,
+    tagger: Option<String>,
+) -> anyhow::Result<()> {
+    validate_tag_name(name)?;
+    if read_tag_ref(name).is_some() {
+        bail!("Tag '{}' already exists.", name);
+    }
+
+    let commit = resolve_commit(rev.unwrap_or("HEAD"))?;
+    let commit_message = load_commit(&commit)?.message;
+
+    let tag = Tag::new(
+        name.to_string(),
+        commit.clone(),
+        message.unwrap_or(name).to_string(),
+        tagger,
+    );
+    let hash = save_tag(&tag)?;
+    write_tag_ref(name, &hash)?;
+
+    print_success(&format!(
+        "Tagged {} \"{}\" as '{}'.",
+        short_hash(&commit, 8),
+        commit_message,
+        name
+    ));
+    print_info(&format!(
+        "Run {} to publish it.",
+        style("`envy push`").cyan()
+    ));
+
+    Ok(())
+}
+
+async fn fetch_remote_index(
+    client: &reqwest::Client,
+    server: &str,
+    token: &str,
+    project_id: &str,
+) -> anyhow::Result<(Option<String>, TagIndex)> {
+    let Some(hash) = fetch_remote_head(client, server, token, project_id, TAG_INDEX_REF).await?
+    else {
+        return Ok((None, TagIndex::default()));
+    };
+
+    if !tag_object_exists(&hash) {
+        download_tag_object(client, server, token, project_id, &hash).await?;
+    }
+    let index = load_tag_index(&hash)?;
+
+    Ok((Some(hash), index))
+}
+
+/// Downloads tags published since the last fetch. A remote tag that differs
+/// from a readable local one of the same name is reported and left alone.
+pub async fn fetch_tags(
+    client: &reqwest::Client,
+    server: &str,
+    token: &str,
+    project_id: &str,
+) -> anyhow::Result<()> {
+    let (index_hash, index) = fetch_remote_index(client, server, token, project_id).await?;
+    let Some(index_hash) = index_hash else {
+        return Ok(());
+    };
+    if read_remote_tags().as_ref() == Some(&index_hash) {
+        return Ok(());
+    }
+
+    let mut fetched = 0;
+    for (name, hash) in &index.tags {
+        validate_tag_name(name)?;
+
+        match read_tag_ref(name) {
+            Some(local) if local == *hash => continue,
+            // A local tag that no longer decrypts (after a rekey) is replaced.
+            Some(local) if try_load_tag(&local).is_some() => {
+                print_warn(&format!(
+                    "Tag '{}' differs from the remote one; keeping the local tag.",
+                    name
+                ));
+                continue;
+            }
+            _ => {}
+        }
+
+        if !tag_object_exists(hash) {
+            download_tag_object(client, server, token, project_id, hash).await?;
+        }
+        let tag = load_tag(hash)?;
+        if tag.name != *name {
+            bail!(
+                "Remote tag '{}' points to a tag object named '{}'.",
+                name,
+                tag.name
+            );
+        }
+
+        write_tag_ref(name, hash)?;
+        fetched += 1;
+    }
+
+    write_remote_tags(&index_hash)?;
+    if fetched > 0 {
+        print_success(&format!("Fetched {} tag(s).", fetched));
+    }
+
+    Ok(())
+}
+
+/// Publishes local tags whose commits are in the pushed history of the current
+/// branch, returning how many were added to the remote tag index.
+pub async fn push_tags(
+    client: &reqwest::Client,
+    server: &str,
+    token: &str,
+    project_id: &str,
+) -> anyhow::Result<usize> {
+    let local = list_tags();
+    if local.is_empty() {
+        return Ok(0);
+    }
+
+    let (index_hash, mut index) = fetch_remote_index(client, server, token, project_id).await?;
+    let pushed_head = read_remote_head();
+
+    let mut added = BTreeMap::new();
+    for (name, hash) in local {
+        match index.tags.get(&name) {
+            Some(remote) if *remote == hash => continue,
+            Some(_) => {
+                print_warn(&format!(
+                    "Tag '{}' already exists on the remote with a different commit; not pushed.",
+                    name
+                ));
+                continue;
+            }
+            None => {}
+        }
+
+        let tag = load_tag(&hash)?;
+        if !pushed_head
+            .as_ref()
+            .is_some_and(|head| is_known_ancestor(&tag.commit, head))
+        {
+            print_info(&format!(
+                "Tag '{}' is not pushed because commit {} is not on this branch's remote.",
+                name,
+                short_hash(&tag.commit, 8)
+            ));
+            continue;
+        }
+
+        added.insert(name, hash);
+    }
+
+    if added.is_empty() {
+        return Ok(0);
+    }
+
+    for hash in added.values() {
+        upload_tag_object(
+            client,
+            server,
+            token,
+            project_id,
+            hash,
+            &tag_blob_path(hash),
+        )
+        .await?;
+    }
+
+    let count = added.len();
+    index.tags.extend(added);
+    let new_index = save_tag_index(&index)?;
+    upload_tag_object(
+        client,
+        server,
+        token,
+        project_id,
+        &new_index,
+        &tag_blob_path(&new_index),
+    )
+    .await?;
+
+    update_remote_head(
+        client,
+        server,
+        token,
+        project_id,
+        TAG_INDEX_REF,
+        &new_index,
+        index_hash.as_deref(),
+    )
+    .await
+    .map_err(|e| anyhow::anyhow!("Failed to update remote tags: {}", e))?;
+    write_remote_tags(&new_index)?;
+
+    Ok(count)
+}
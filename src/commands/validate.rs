@@ -0,0 +1,122 @@
+use anyhow::bail;
+use zeroize::Zeroize;
+
+use crate::{
+    commands::crypto::unlock_tracked_file_with,
+    utils::{
+        commit::load_commit_manifest,
+        dotenv::parse_env,
+        manifest::{Manifest, load_manifest},
+        paths::normalize_path,
+        profile::Profile,
+        project_config::{ProjectConfig, load_project_config},
+        schema::{SCHEMA_PATH, check, load_schema},
+        ui::{is_interactive, print_error, print_info, print_success},
+    },
+};
+
+/// Passphrase for a passphrase-mode file, taken from the `passphrase_env` of a
+/// profile that includes it.
+fn env_passphrase(project: &ProjectConfig, path: &str) -> anyhow::Result<Option<String>> {
+    for name in project.profiles.keys() {
+        let profile = Profile::load(project, name)?;
+        if profile.includes(path)
+            && let Some(passphrase) = profile.file_passphrase()
+        {
+            return Ok(Some(passphrase));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Checks the tracked files of `manifest` against the schema and prints each
+/// problem. Returns the number of problems found. Passphrase-protected files
+/// use a profile's `passphrase_env` when set and are prompted for otherwise.
+/// Without `prompt`, a passphrase is never read from piped input, so the check
+/// fails instead when no terminal is available.
+fn check_manifest(manifest: &Manifest, paths: &[String], prompt: bool) -> anyhow::Result<usize> {
+    let Some(schema) = load_schema()? else {
+        return Ok(0);
+    };
+    let project = load_project_config()?;
+    let selected: Vec<String> = paths.iter().map(|path| normalize_path(path)).collect();
+
+    let mut problems = 0;
+
+    for (path, entry) in &manifest.files {
+        if !selected.is_empty() && !selected.contains(path) {
+            continue;
+        }
+
+        let rules = schema.rules_for(path, &project)?;
+        if rules.is_empty() {
+            continue;
+        }
+
+        let passphrase = match entry.key {
+            Some(_) => None,
+            None => env_passphrase(&project, path)?,
+        };
+        if entry.key.is_none() && passphrase.is_none() && !prompt && !is_interactive() {
+            bail!(
+                "Cannot validate '{}': it has its own passphrase and there is no terminal to ask for it. Set `passphrase_env` on a profile that includes it, or pass `--no-verify`.",
+                path
+            );
+        }
+
+        let (mut plaintext, _) = unlock_tracked_file_with(manifest, path, passphrase)?;
+        let parsed = parse_env(&plaintext, path);
+        plaintext.zeroize();
+        let mut vars = parsed?;
+
+        let issues = check(&vars, &rules);
+        for (_, value) in vars.iter_mut() {
+            value.zeroize();
+        }
+
+        for issue in &issues {
+            print_error(&format!("{}: {} {}", path, issue.key, issue.message));
+        }
+        problems += issues.len();
+    }
+
+    Ok(problems)
+}
+
+pub fn validate(paths: &[String], commit: Option<&str>) -> anyhow::Result<()> {
+    let manifest = match commit {
+        Some(rev) => load_commit_manifest(rev)?,
+        None => load_manifest()?,
+    };
+
+    if load_schema()?.is_none() {
+        print_info(&format!(
+            "No schema found. Create {} to declare keys.",
+            SCHEMA_PATH
+        ));
+        return Ok(());
+    }
+
+    let problems = check_manifest(&manifest, paths, true)?;
+    if problems > 0 {
+        bail!("{} problem(s) found.", problems);
+    }
+
+    print_success("All tracked files match the schema.");
+    Ok(())
+}
+
+/// Refuses to `action` (commit or push) when `manifest` breaks the schema.
+pub fn verify_before(action: &str, manifest: &Manifest) -> anyhow::Result<()> {
+    let problems = check_manifest(manifest, &[], false)?;
+    if problems > 0 {
+        bail!(
+            "Schema validation found {} problem(s). Fix them, or pass `--no-verify` to {} anyway.",
+            problems,
+            action
+        );
+    }
+
+    Ok(())
+}
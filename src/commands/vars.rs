@@ -0,0 +1,133 @@
+use std::io::Write;
+
+use anyhow::bail;
+use console::style;
+use zeroize::Zeroize;
+
+use crate::{
+    commands::{
+        commit::commit,
+        crypto::{store_tracked_file, unlock_tracked_file},
+    },
+    utils::{
+        dotenv::{is_valid_key, parse_env, set_var, unset_var},
+        manifest::{load_manifest, save_manifest},
+        paths::{normalize_path, to_native_path},
+        ui::{print_info, print_success},
+    },
+};
+
+/// Decrypts a tracked file, applies `edit` to its text in memory and
+/// re-encrypts the result with the same key, optionally committing it.
+/// Nothing is written when the edit leaves the contents unchanged.
+pub fn edit_tracked_file(
+    file: &str,
+    message: Option<&str>,
+    edit: impl FnOnce(&str) -> anyhow::Result<String>,
+) -> anyhow::Result<()> {
+    let mut manifest = load_manifest()?;
+    let path = normalize_path(file);
+
+    let (plaintext, key) = unlock_tracked_file(&manifest, &path)?;
+    let mut contents = String::from_utf8(plaintext).map_err(|e| {
+        e.into_bytes().zeroize();
+        anyhow::anyhow!("'{}' is not valid UTF-8", path)
+    })?;
+
+    let result = edit(&contents);
+    let unchanged = result.as_ref().is_ok_and(|updated| *updated == contents);
+    contents.zeroize();
+    let mut updated = result?;
+
+    if unchanged {
+        updated.zeroize();
+        print_info(&format!("No changes to '{}'.", path));
+        return Ok(());
+    }
+
+    let mode = manifest.files.get(&path).and_then(|entry| entry.mode);
+    let stored = store_tracked_file(&mut manifest, &path, updated.as_bytes(), mode, &key);
+    updated.zeroize();
+    stored?;
+
+    save_manifest(&manifest)?;
+    print_success(&format!("Updated '{}'.", path));
+
+    if to_native_path(&path).is_file() {
+        print_info(&format!(
+            "The local copy of '{}' was not changed. Run {} to refresh it.",
+            path,
+            style(format!("`envy restore {}`", path)).cyan()
+        ));
+    }
+
+    match message {
+        Some(message) => commit(message, None, false),
+        None => {
+            print_info(&format!(
+                "Run {} to record this change.",
+                style("`envy commit -m \"message\"`").cyan()
+            ));
+            Ok(())
+        }
+    }
+}
+
+pub fn set(file: &str, assignments: &[String], message: Option<&str>) -> anyhow::Result<()> {
+    let mut pairs = Vec::new();
+    for assignment in assignments {
+        let Some((key, value)) = assignment.split_once('=') else {
+            bail!("Expected KEY=VALUE, got '{}'", assignment);
+        };
+        if !is_valid_key(key) {
+            bail!("Invalid key '{}'", key);
+        }
+        pairs.push((key, value));
+    }
+
+    edit_tracked_file(file, message, |contents| {
+        let mut updated = contents.to_string();
+        for (key, value) in &pairs {
+            let next = set_var(&updated, key, value);
+            updated.zeroize();
+            updated = next;
+        }
+        Ok(updated)
+    })
+}
+
+pub fn unset(file: &str, keys: &[String], message: Option<&str>) -> anyhow::Result<()> {
+    edit_tracked_file(file, message, |contents| {
+        let mut updated = contents.to_string();
+        for key in keys {
+            let Some(next) = unset_var(&updated, key) else {
+                updated.zeroize();
+                bail!("Key '{}' is not set in '{}'", key, normalize_path(file));
+            };
+            updated.zeroize();
+            updated = next;
+        }
+        Ok(updated)
+    })
+}
+
+pub fn get(file: &str, key: &str) -> anyhow::Result<()> {
+    let manifest = load_manifest()?;
+    let path = normalize_path(file);
+
+    let (mut plaintext, _) = unlock_tracked_file(&manifest, &path)?;
+    let vars = parse_env(&plaintext, &path);
+    plaintext.zeroize();
+
+    let value = vars?
+        .into_iter()
+        .rev()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value)
+        .ok_or_else(|| anyhow::anyhow!("Key '{}' is not set in '{}'", key, path))?;
+
+    let mut stdout = std::io::stdout().lock();
+    writeln!(stdout, "{}", value)
+        .and_then(|_| stdout.flush())
+        .map_err(|e| anyhow::anyhow!("Failed to write to stdout: {}", e))
+}
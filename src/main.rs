@@ -3,8 +3,12 @@ use std::path::Path;
 
 use clap::{Parser, Subcommand};
 
+use crate::commands::crypto::FileKey;
+use crate::commands::history::ResetMode;
 use crate::commands::update::{check_for_update, print_update_notification};
 use crate::commands::{auth::logout_command, status::status};
+use crate::utils::export::ExportFormat;
+use crate::utils::import::ImportFormat;
 use crate::utils::session::set_passphrase_override;
 use crate::utils::ui::{
     generate_secure_passphrase, print_error, print_info, print_success, prompt_input_with_default,
@@ -44,10 +48,26 @@ enum MemberCommand {
 #[derive(Subcommand)]
 enum Commands {
     Encrypt {
-        #[arg(short, long, default_value = ".env")]
-        input: String,
+        paths: Vec<String>,
         #[arg(short, long)]
+        input: Option<String>,
+        #[arg(short, long, value_name = "DIR")]
+        recursive: Vec<String>,
+        #[arg(short, long, conflicts_with = "project_key")]
         passphrase: Option<String>,
+        #[arg(long)]
+        project_key: bool,
+        #[arg(long, conflicts_with_all = ["project_key", "passphrase"])]
+        per_file: bool,
+    },
+    Add {
+        paths: Vec<String>,
+        #[arg(short, long, conflicts_with = "project_key")]
+        passphrase: Option<String>,
+        #[arg(long)]
+        project_key: bool,
+        #[arg(long, conflicts_with_all = ["project_key", "passphrase"])]
+        per_file: bool,
     },
     Remove {
         #[arg(short, long, default_value = ".env")]
@@ -55,7 +75,6 @@ enum Commands {
         #[arg(short, long)]
         passphrase: Option<String>,
     },
-    // Decrypt {},
     Init {
         #[arg(short, long)]
         name: Option<String>,
@@ -69,15 +88,25 @@ enum Commands {
         remote: Option<String>,
         #[arg(short, long)]
         passphrase: Option<String>,
+        #[arg(long)]
+        no_verify: bool,
     },
     Pull {
         remote: Option<String>,
         #[arg(short, long)]
         passphrase: Option<String>,
+        #[arg(long)]
+        allow_rewind: bool,
+        #[arg(long)]
+        env: Option<String>,
+        #[arg(long = "ref", value_name = "REF")]
+        git_ref: Option<String>,
     },
     Status {
         #[arg(short, long)]
         passphrase: Option<String>,
+        #[arg(long)]
+        env: Option<String>,
     },
     Doctor {
         remote: Option<String>,
@@ -99,6 +128,15 @@ enum Commands {
         author: Option<String>,
         #[arg(short, long)]
         passphrase: Option<String>,
+        #[arg(long)]
+        no_verify: bool,
+    },
+    Validate {
+        paths: Vec<String>,
+        #[arg(short, long)]
+        commit: Option<String>,
+        #[arg(short, long)]
+        passphrase: Option<String>,
     },
     Log {
         #[arg(short, long, default_value = "10")]
@@ -106,12 +144,276 @@ enum Commands {
         #[arg(short, long)]
         passphrase: Option<String>,
     },
+    Run {
+        #[arg(short, long)]
+        file: Vec<String>,
+        #[arg(short, long)]
+        passphrase: Option<String>,
+        #[arg(long)]
+        env: Option<String>,
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
+    Rekey {
+        remote: Option<String>,
+        #[arg(short, long)]
+        passphrase: Option<String>,
+        #[arg(short, long)]
+        new_passphrase: Option<String>,
+    },
+    #[command(alias = "decrypt")]
+    Cat {
+        path: String,
+        #[arg(short, long)]
+        commit: Option<String>,
+        #[arg(short, long)]
+        output: Option<String>,
+        #[arg(short, long)]
+        passphrase: Option<String>,
+    },
+    Restore {
+        #[arg(required = true)]
+        paths: Vec<String>,
+        #[arg(short, long)]
+        source: Option<String>,
+        #[arg(short, long)]
+        passphrase: Option<String>,
+    },
+    Branch {
+        name: Option<String>,
+        #[arg(conflicts_with = "delete")]
+        start: Option<String>,
+        #[arg(short, long, requires = "name")]
+        delete: bool,
+        #[arg(long, requires = "delete")]
+        force: bool,
+        #[arg(short, long)]
+        passphrase: Option<String>,
+    },
+    Switch {
+        name: String,
+        #[arg(short, long)]
+        create: bool,
+        #[arg(short, long)]
+        passphrase: Option<String>,
+    },
+    Tag {
+        name: Option<String>,
+        commit: Option<String>,
+        #[arg(short, long, requires = "name")]
+        message: Option<String>,
+        #[arg(short, long, requires = "name")]
+        author: Option<String>,
+        #[arg(short, long)]
+        passphrase: Option<String>,
+    },
+    Checkout {
+        commit: String,
+        #[arg(short, long)]
+        passphrase: Option<String>,
+    },
+    Reset {
+        #[arg(default_value = "HEAD")]
+        commit: String,
+        #[arg(long, conflicts_with = "hard")]
+        soft: bool,
+        #[arg(long)]
+        hard: bool,
+        #[arg(short, long)]
+        passphrase: Option<String>,
+    },
+    Revert {
+        commit: String,
+        #[arg(short, long)]
+        message: Option<String>,
+        #[arg(short, long)]
+        author: Option<String>,
+        #[arg(short, long)]
+        passphrase: Option<String>,
+        #[arg(long)]
+        no_verify: bool,
+    },
+    Example {
+        #[arg(short, long, default_value = ".env")]
+        file: String,
+        #[arg(short, long)]
+        output: Option<String>,
+        #[arg(long)]
+        check: bool,
+        #[arg(short, long)]
+        passphrase: Option<String>,
+    },
+    Import {
+        #[arg(required_unless_present = "recursive")]
+        source: Option<String>,
+        #[arg(long, value_enum, default_value = "dotenv")]
+        from: ImportFormat,
+        #[arg(
+            long = "as",
+            value_name = "TRACKED_PATH",
+            required_unless_present = "recursive"
+        )]
+        as_path: Option<String>,
+        #[arg(short, long, value_name = "DIR", conflicts_with = "source")]
+        recursive: Option<String>,
+        #[arg(short, long, conflicts_with = "project_key")]
+        passphrase: Option<String>,
+        #[arg(long)]
+        project_key: bool,
+    },
+    Export {
+        #[arg(short, long, default_value = ".env")]
+        file: String,
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+        #[arg(short, long)]
+        commit: Option<String>,
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(short, long)]
+        passphrase: Option<String>,
+    },
+    Diff {
+        #[arg(num_args = 0..=2)]
+        commits: Vec<String>,
+        #[arg(long)]
+        staged: bool,
+        #[arg(long)]
+        show_values: bool,
+        #[arg(short, long)]
+        passphrase: Option<String>,
+        #[arg(last = true)]
+        paths: Vec<String>,
+    },
+    Edit {
+        #[arg(default_value = ".env")]
+        path: String,
+        #[arg(short, long)]
+        message: Option<String>,
+        #[arg(short, long)]
+        passphrase: Option<String>,
+    },
+    Set {
+        #[arg(required = true, value_name = "KEY=VALUE")]
+        assignments: Vec<String>,
+        #[arg(short, long, default_value = ".env")]
+        file: String,
+        #[arg(short, long)]
+        message: Option<String>,
+        #[arg(short, long)]
+        passphrase: Option<String>,
+    },
+    Get {
+        key: String,
+        #[arg(short, long, default_value = ".env")]
+        file: String,
+        #[arg(short, long)]
+        passphrase: Option<String>,
+    },
+    Unset {
+        #[arg(required = true)]
+        keys: Vec<String>,
+        #[arg(short, long, default_value = ".env")]
+        file: String,
+        #[arg(short, long)]
+        message: Option<String>,
+        #[arg(short, long)]
+        passphrase: Option<String>,
+    },
+}
+
+/// Passphrase for a file encrypted without the project key: the `-p` value if
+/// given, otherwise prompted for with a generated default.
+fn read_file_passphrase(target: &str, cli_passphrase: Option<String>) -> String {
+    if let Some(pass) = cli_passphrase {
+        if pass.len() < 6 {
+            print_error("Passphrase must be at least 6 characters long");
+            std::process::exit(1);
+        }
+        return pass;
+    }
+
+    let default_passphrase = generate_secure_passphrase(16);
+
+    match prompt_input_with_default(
+        &format!("Enter passphrase to encrypt {}", target),
+        &default_passphrase,
+        Some(|input: &String| {
+            if input.len() < 6 {
+                Err("Must be at least 6 characters long".to_string())
+            } else {
+                Ok(())
+            }
+        }),
+    ) {
+        Ok(pass) => pass,
+        Err(e) => {
+            print_error(&format!("Failed to read passphrase: {}", e));
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Pairs each file with its key: the project key, one passphrase for the
+/// batch, or with `per_file`, a passphrase asked for each file.
+fn file_keys(
+    targets: Vec<String>,
+    project_key: bool,
+    per_file: bool,
+    cli_passphrase: Option<String>,
+) -> Vec<(String, FileKey)> {
+    if project_key {
+        return targets
+            .into_iter()
+            .map(|path| (path, FileKey::Project))
+            .collect();
+    }
+
+    if per_file {
+        return targets
+            .into_iter()
+            .map(|path| {
+                let passphrase = read_file_passphrase(&path, None);
+                (path, FileKey::Passphrase(passphrase))
+            })
+            .collect();
+    }
+
+    let label = match targets.as_slice() {
+        [path] => path.clone(),
+        _ => format!("{} files", targets.len()),
+    };
+    let passphrase = read_file_passphrase(&label, cli_passphrase);
+    targets
+        .into_iter()
+        .map(|path| (path, FileKey::Passphrase(passphrase.clone())))
+        .collect()
+}
+
+fn report_encrypted(files: &[(String, FileKey)], project_key: bool) {
+    match files {
+        [_] if project_key => print_success("File encrypted successfully with the project key"),
+        [_] => print_success("File encrypted successfully"),
+        _ => {
+            for (path, _) in files {
+                print_info(&format!("Encrypted '{}'", path));
+            }
+            print_success(&format!("{} files encrypted successfully", files.len()));
+        }
+    }
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
-    let is_update_command = matches!(cli.command, Commands::Update {});
+    // Commands that stream plaintext to stdout must not have the update notice appended.
+    let skip_update_check = matches!(
+        cli.command,
+        Commands::Update {}
+            | Commands::Cat { output: None, .. }
+            | Commands::Get { .. }
+            | Commands::Export { .. }
+    );
 
     match cli.command {
         Commands::Update {} => {
@@ -213,53 +515,280 @@ fn main() -> anyhow::Result<()> {
             }
         },
         Commands::Encrypt {
+            paths,
             input,
+            recursive,
             passphrase: cli_passphrase,
+            project_key,
+            per_file,
         } => {
             utils::initialized::check_initialized()?;
-            let default_passphrase = generate_secure_passphrase(16);
 
-            let passphrase = if let Some(pass) = cli_passphrase {
-                if pass.len() < 6 {
-                    print_error("Passphrase must be at least 6 characters long");
+            let mut patterns: Vec<String> = input.into_iter().chain(paths).collect();
+            if patterns.is_empty() && recursive.is_empty() {
+                patterns.push(".env".to_string());
+            }
+
+            let targets = match utils::worktree::expand_targets(&patterns, &recursive) {
+                Ok(targets) => targets,
+                Err(e) => {
+                    print_error(&format!("Encrypt failed: {}", e));
                     std::process::exit(1);
                 }
-                pass
-            } else {
-                match prompt_input_with_default(
-                    &format!("Enter passphrase to encrypt {}", input),
-                    &default_passphrase,
-                    Some(|input: &String| {
-                        if input.len() < 6 {
-                            Err("Must be at least 6 characters long".to_string())
-                        } else {
-                            Ok(())
-                        }
-                    }),
-                ) {
-                    Ok(pass) => pass,
-                    Err(e) => {
-                        print_error(&format!("Failed to read passphrase: {}", e));
-                        std::process::exit(1);
-                    }
+            };
+
+            let files = file_keys(targets, project_key, per_file, cli_passphrase);
+            commands::crypto::encrypt_files(&files)?;
+            report_encrypted(&files, project_key);
+        }
+        Commands::Add {
+            paths,
+            passphrase: cli_passphrase,
+            project_key,
+            per_file,
+        } => {
+            utils::initialized::check_initialized()?;
+
+            let targets = match commands::add::add_targets(&paths) {
+                Ok(targets) => targets,
+                Err(e) => {
+                    print_error(&format!("Add failed: {}", e));
+                    std::process::exit(1);
                 }
             };
 
-            commands::crypto::encrypt_file(&input, &passphrase)?;
-            print_success("File encrypted successfully");
+            if targets.is_empty() {
+                print_info("Nothing to add. Every file matching the tracking rules is tracked.");
+                return Ok(());
+            }
+
+            let files = file_keys(targets, project_key, per_file, cli_passphrase);
+            commands::crypto::encrypt_files(&files)?;
+            report_encrypted(&files, project_key);
+        }
+        Commands::Restore {
+            paths,
+            source,
+            passphrase: cli_passphrase,
+        } => {
+            utils::initialized::check_initialized()?;
+
+            if cli_passphrase.is_some() {
+                set_passphrase_override(cli_passphrase);
+            }
+
+            let result = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(async { commands::restore::restore(&paths, source.as_deref()).await });
+
+            if let Err(e) = result {
+                print_error(&format!("Restore failed: {}", e));
+                std::process::exit(1);
+            }
+        }
+        Commands::Branch {
+            name,
+            start,
+            delete,
+            force,
+            passphrase: cli_passphrase,
+        } => {
+            utils::initialized::check_initialized()?;
+
+            if cli_passphrase.is_some() {
+                set_passphrase_override(cli_passphrase);
+            }
+
+            let result = match name {
+                Some(name) if delete => commands::branch::delete(&name, force),
+                Some(name) => commands::branch::create(&name, start.as_deref()),
+                None => commands::branch::list(),
+            };
+
+            if let Err(e) = result {
+                print_error(&format!("Branch failed: {}", e));
+                std::process::exit(1);
+            }
         }
+        Commands::Switch {
+            name,
+            create,
+            passphrase: cli_passphrase,
+        } => {
+            utils::initialized::check_initialized()?;
 
-        // Deprecated since v0.1.5
-        // Commands::Decrypt {} => {
-        //     utils::initialized::check_initialized()?;
-        //     let passphrase = Password::new().with_prompt("Enter passphrase").interact()?;
+            if cli_passphrase.is_some() {
+                set_passphrase_override(cli_passphrase);
+            }
 
-        //     commands::crypto::decrypt_files(&passphrase)?;
-        //     print_success("File decrypted successfully");
-        // }
+            let result = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(async { commands::branch::switch(&name, create).await });
+
+            if let Err(e) = result {
+                print_error(&format!("Switch failed: {}", e));
+                std::process::exit(1);
+            }
+        }
+        Commands::Tag {
+            name,
+            commit,
+            message,
+            author,
+            passphrase: cli_passphrase,
+        } => {
+            utils::initialized::check_initialized()?;
+
+            if cli_passphrase.is_some() {
+                set_passphrase_override(cli_passphrase);
+            }
+
+            let result = match name {
+                Some(name) => {
+                    commands::tag::create(&name, commit.as_deref(), message.as_deref(), author)
+                }
+                None => commands::tag::list(),
+            };
+
+            if let Err(e) = result {
+                print_error(&format!("Tag failed: {}", e));
+                std::process::exit(1);
+            }
+        }
+        Commands::Checkout {
+            commit,
+            passphrase: cli_passphrase,
+        } => {
+            utils::initialized::check_initialized()?;
+
+            if cli_passphrase.is_some() {
+                set_passphrase_override(cli_passphrase);
+            }
+
+            let result = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(async { commands::history::checkout(&commit).await });
+
+            if let Err(e) = result {
+                print_error(&format!("Checkout failed: {}", e));
+                std::process::exit(1);
+            }
+        }
+        Commands::Reset {
+            commit,
+            soft,
+            hard,
+            passphrase: cli_passphrase,
+        } => {
+            utils::initialized::check_initialized()?;
+
+            if cli_passphrase.is_some() {
+                set_passphrase_override(cli_passphrase);
+            }
+
+            let mode = if soft {
+                ResetMode::Soft
+            } else if hard {
+                ResetMode::Hard
+            } else {
+                ResetMode::Mixed
+            };
+
+            let result = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(async { commands::history::reset(&commit, mode).await });
+
+            if let Err(e) = result {
+                print_error(&format!("Reset failed: {}", e));
+                std::process::exit(1);
+            }
+        }
+        Commands::Revert {
+            commit,
+            message,
+            author,
+            passphrase: cli_passphrase,
+            no_verify,
+        } => {
+            utils::initialized::check_initialized()?;
+
+            if cli_passphrase.is_some() {
+                set_passphrase_override(cli_passphrase);
+            }
+
+            let result = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(async {
+                    commands::history::revert(&commit, message.as_deref(), author, no_verify).await
+                });
+
+            if let Err(e) = result {
+                print_error(&format!("Revert failed: {}", e));
+                std::process::exit(1);
+            }
+        }
+        Commands::Example {
+            file,
+            output,
+            check,
+            passphrase: cli_passphrase,
+        } => {
+            utils::initialized::check_initialized()?;
+
+            if cli_passphrase.is_some() {
+                set_passphrase_override(cli_passphrase);
+            }
+
+            if let Err(e) = commands::example::example(&file, output.as_deref(), check) {
+                print_error(&format!("Example failed: {}", e));
+                std::process::exit(1);
+            }
+        }
+        Commands::Import {
+            source,
+            from,
+            as_path,
+            recursive,
+            passphrase: cli_passphrase,
+            project_key,
+        } => {
+            utils::initialized::check_initialized()?;
+
+            let key = if project_key {
+                FileKey::Project
+            } else {
+                let target = as_path
+                    .as_deref()
+                    .or(recursive.as_deref())
+                    .unwrap_or(".env");
+                FileKey::Passphrase(read_file_passphrase(target, cli_passphrase))
+            };
+
+            if let Err(e) = commands::import::import(
+                from,
+                source.as_deref(),
+                as_path.as_deref(),
+                recursive.as_deref(),
+                &key,
+            ) {
+                print_error(&format!("Import failed: {}", e));
+                std::process::exit(1);
+            }
+        }
         Commands::Push {
             remote,
             passphrase: cli_passphrase,
+            no_verify,
         } => {
             utils::initialized::check_initialized()?;
 
@@ -271,7 +800,7 @@ fn main() -> anyhow::Result<()> {
                 .enable_all()
                 .build()
                 .unwrap()
-                .block_on(async { commands::push::push(remote.as_deref()).await });
+                .block_on(async { commands::push::push(remote.as_deref(), no_verify).await });
 
             if let Err(e) = result {
                 print_error(&format!("Push failed: {}", e));
@@ -302,6 +831,9 @@ fn main() -> anyhow::Result<()> {
         Commands::Pull {
             remote,
             passphrase: cli_passphrase,
+            allow_rewind,
+            env,
+            git_ref,
         } => {
             utils::initialized::check_initialized()?;
 
@@ -313,7 +845,15 @@ fn main() -> anyhow::Result<()> {
                 .enable_all()
                 .build()
                 .unwrap()
-                .block_on(async { commands::pull::pull(remote.as_deref()).await });
+                .block_on(async {
+                    commands::pull::pull(
+                        remote.as_deref(),
+                        allow_rewind,
+                        env.as_deref(),
+                        git_ref.as_deref(),
+                    )
+                    .await
+                });
 
             if let Err(e) = result {
                 print_error(&format!("Pull failed: {}", e));
@@ -322,6 +862,7 @@ fn main() -> anyhow::Result<()> {
         }
         Commands::Status {
             passphrase: cli_passphrase,
+            env,
         } => {
             utils::initialized::check_initialized()?;
 
@@ -333,7 +874,7 @@ fn main() -> anyhow::Result<()> {
                 .enable_all()
                 .build()?;
 
-            rt.block_on(status())?;
+            rt.block_on(status(env.as_deref()))?;
         }
         Commands::Doctor {
             remote,
@@ -415,6 +956,7 @@ fn main() -> anyhow::Result<()> {
             message,
             author,
             passphrase: cli_passphrase,
+            no_verify,
         } => {
             utils::initialized::check_initialized()?;
 
@@ -422,11 +964,27 @@ fn main() -> anyhow::Result<()> {
                 set_passphrase_override(cli_passphrase);
             }
 
-            if let Err(e) = commands::commit::commit(&message, author) {
+            if let Err(e) = commands::commit::commit(&message, author, no_verify) {
                 print_error(&format!("Commit failed: {}", e));
                 std::process::exit(1);
             }
         }
+        Commands::Validate {
+            paths,
+            commit,
+            passphrase: cli_passphrase,
+        } => {
+            utils::initialized::check_initialized()?;
+
+            if cli_passphrase.is_some() {
+                set_passphrase_override(cli_passphrase);
+            }
+
+            if let Err(e) = commands::validate::validate(&paths, commit.as_deref()) {
+                print_error(&format!("Validation failed: {}", e));
+                std::process::exit(1);
+            }
+        }
         Commands::Log {
             count,
             passphrase: cli_passphrase,
@@ -442,9 +1000,171 @@ fn main() -> anyhow::Result<()> {
                 std::process::exit(1);
             }
         }
+        Commands::Run {
+            file,
+            passphrase: cli_passphrase,
+            env,
+            command,
+        } => {
+            utils::initialized::check_initialized()?;
+
+            if cli_passphrase.is_some() {
+                set_passphrase_override(cli_passphrase);
+            }
+
+            if let Err(e) = commands::run::run(&file, &command, env.as_deref()) {
+                print_error(&format!("Run failed: {}", e));
+                std::process::exit(1);
+            }
+        }
+        Commands::Rekey {
+            remote,
+            passphrase: cli_passphrase,
+            new_passphrase,
+        } => {
+            utils::initialized::check_initialized()?;
+
+            if cli_passphrase.is_some() {
+                set_passphrase_override(cli_passphrase);
+            }
+
+            let result = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(async {
+                    commands::rekey::rekey(remote.as_deref(), new_passphrase).await
+                });
+
+            if let Err(e) = result {
+                print_error(&format!("Rekey failed: {}", e));
+                std::process::exit(1);
+            }
+        }
+        Commands::Cat {
+            path,
+            commit,
+            output,
+            passphrase: cli_passphrase,
+        } => {
+            utils::initialized::check_initialized()?;
+
+            if cli_passphrase.is_some() {
+                set_passphrase_override(cli_passphrase);
+            }
+
+            if let Err(e) = commands::cat::cat(&path, commit.as_deref(), output.as_deref()) {
+                print_error(&format!("Cat failed: {}", e));
+                std::process::exit(1);
+            }
+        }
+        Commands::Export {
+            file,
+            format,
+            commit,
+            name,
+            passphrase: cli_passphrase,
+        } => {
+            utils::initialized::check_initialized()?;
+
+            if cli_passphrase.is_some() {
+                set_passphrase_override(cli_passphrase);
+            }
+
+            if let Err(e) =
+                commands::export::export(&file, format, commit.as_deref(), name.as_deref())
+            {
+                print_error(&format!("Export failed: {}", e));
+                std::process::exit(1);
+            }
+        }
+        Commands::Diff {
+            commits,
+            staged,
+            show_values,
+            passphrase: cli_passphrase,
+            paths,
+        } => {
+            utils::initialized::check_initialized()?;
+
+            if cli_passphrase.is_some() {
+                set_passphrase_override(cli_passphrase);
+            }
+
+            if let Err(e) = commands::diff::diff(&commits, staged, &paths, show_values) {
+                print_error(&format!("Diff failed: {}", e));
+                std::process::exit(1);
+            }
+        }
+        Commands::Edit {
+            path,
+            message,
+            passphrase: cli_passphrase,
+        } => {
+            utils::initialized::check_initialized()?;
+
+            if cli_passphrase.is_some() {
+                set_passphrase_override(cli_passphrase);
+            }
+
+            if let Err(e) = commands::edit::edit(&path, message.as_deref()) {
+                print_error(&format!("Edit failed: {}", e));
+                std::process::exit(1);
+            }
+        }
+        Commands::Set {
+            assignments,
+            file,
+            message,
+            passphrase: cli_passphrase,
+        } => {
+            utils::initialized::check_initialized()?;
+
+            if cli_passphrase.is_some() {
+                set_passphrase_override(cli_passphrase);
+            }
+
+            if let Err(e) = commands::vars::set(&file, &assignments, message.as_deref()) {
+                print_error(&format!("Set failed: {}", e));
+                std::process::exit(1);
+            }
+        }
+        Commands::Get {
+            key,
+            file,
+            passphrase: cli_passphrase,
+        } => {
+            utils::initialized::check_initialized()?;
+
+            if cli_passphrase.is_some() {
+                set_passphrase_override(cli_passphrase);
+            }
+
+            if let Err(e) = commands::vars::get(&file, &key) {
+                print_error(&format!("Get failed: {}", e));
+                std::process::exit(1);
+            }
+        }
+        Commands::Unset {
+            keys,
+            file,
+            message,
+            passphrase: cli_passphrase,
+        } => {
+            utils::initialized::check_initialized()?;
+
+            if cli_passphrase.is_some() {
+                set_passphrase_override(cli_passphrase);
+            }
+
+            if let Err(e) = commands::vars::unset(&file, &keys, message.as_deref()) {
+                print_error(&format!("Unset failed: {}", e));
+                std::process::exit(1);
+            }
+        }
     }
 
-    if !is_update_command {
+    if !skip_update_check {
         let rt = tokio::runtime::Builder::new_current_thread()
             .enable_all()
             .build()
@@ -4,9 +4,16 @@ use sha2::{Digest, Sha256};
 use std::fs;
 use std::path::Path;
 
-use crate::commands::crypto::{decrypt_bytes_with_key, encrypt_bytes_with_key};
+use crate::commands::crypto::{
+    ObjectKind, decrypt_bytes_with_key, encrypt_bytes_with_key, object_aad,
+};
 
-use super::manifest::get_project_key;
+use super::manifest::{Manifest, get_project_key, load_manifest_by_hash};
+use super::project_config::load_project_config;
+use super::session::clear_session;
+use super::storage::verify_object_hash;
+use super::tag::resolve_tag;
+use super::ui::short_hash;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Commit {
@@ -44,45 +51,294 @@ impl Commit {
 
 const COMMITS_DIR: &str = ".envoy/cache/commits";
 const HEAD_PATH: &str = ".envoy/HEAD";
+const HEADS_DIR: &str = ".envoy/refs/heads";
+const REMOTE_REFS_DIR: &str = ".envoy/refs/remotes/origin";
 const REMOTE_HEAD_PATH: &str = ".envoy/refs/remotes/origin/HEAD";
-
-pub fn read_head() -> Option<String> {
-    fs::read_to_string(HEAD_PATH)
+const ACCEPTED_DIR: &str = ".envoy/refs/accepted";
+/// Where the default branch's accepted heads were kept before each branch had its own log.
+const LEGACY_ACCEPTED_HEADS_PATH: &str = ".envoy/refs/remotes/origin/accepted";
+/// Accepted heads kept per branch. A server rolling back further than this is
+/// still refused, but reported as a fork rather than a rollback.
+const MAX_ACCEPTED_HEADS: usize = 100;
+const DETACHED_PATH: &str = ".envoy/DETACHED";
+const SYMREF_PREFIX: &str = "ref: refs/heads/";
+
+/// The branch new projects start on. Its remote ref is the server's project
+/// HEAD, so clients without branch support keep working.
+pub const DEFAULT_BRANCH: &str = "main";
+
+fn read_ref(path: impl AsRef<Path>) -> Option<String> {
+    fs::read_to_string(path)
         .ok()
         .map(|s| s.trim().to_string())
         .filter(|s| !s.is_empty())
 }
 
-pub fn write_head(commit_hash: &str) -> Result<()> {
-    if let Some(parent) = Path::new(HEAD_PATH).parent() {
+fn write_ref(path: impl AsRef<Path>, value: &str) -> Result<()> {
+    if let Some(parent) = path.as_ref().parent() {
         fs::create_dir_all(parent)?;
     }
-    fs::write(HEAD_PATH, commit_hash)?;
+    fs::write(path, value)?;
+    Ok(())
+}
+
+/// The branch HEAD points to, or `None` while detached. A HEAD holding a bare
+/// commit hash from before branches existed belongs to the default branch.
+pub fn current_branch() -> Option<String> {
+    match read_ref(HEAD_PATH) {
+        Some(head) => match head.strip_prefix(SYMREF_PREFIX) {
+            Some(branch) => Some(branch.to_string()),
+            None if read_detached().is_some() => None,
+            None => Some(DEFAULT_BRANCH.to_string()),
+        },
+        None => Some(DEFAULT_BRANCH.to_string()),
+    }
+}
+
+/// The branch whose remote ref is used: the current one, or the one that
+/// was left by `envy checkout` while detached.
+pub fn upstream_branch() -> String {
+    current_branch()
+        .or_else(read_detached)
+        .unwrap_or_else(|| DEFAULT_BRANCH.to_string())
+}
+
+pub fn validate_branch_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 100
+        && !name.starts_with(['.', '-'])
+        && !name.eq_ignore_ascii_case("HEAD")
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+
+    if !valid {
+        bail!(
+            "Invalid branch name '{}'. Use letters, digits, '.', '_' and '-'.",
+            name
+        );
+    }
+    Ok(())
+}
+
+pub fn read_branch(name: &str) -> Option<String> {
+    read_ref(Path::new(HEADS_DIR).join(name)).or_else(|| {
+        // Legacy layout: the default branch's commit is stored in HEAD itself.
+        let head = read_ref(HEAD_PATH)?;
+        (name == DEFAULT_BRANCH && !head.starts_with(SYMREF_PREFIX) && read_detached().is_none())
+            .then_some(head)
+    })
+}
+
+pub fn write_branch(name: &str, commit_hash: &str) -> Result<()> {
+    write_ref(Path::new(HEADS_DIR).join(name), commit_hash)
+}
+
+pub fn delete_branch(name: &str) -> Result<()> {
+    fs::remove_file(Path::new(HEADS_DIR).join(name))
+        .map_err(|e| anyhow::anyhow!("Failed to delete branch '{}': {}", name, e))
+}
+
+/// Local branches with their commits, sorted by name.
+pub fn list_branches() -> Vec<(String, String)> {
+    let mut branches: Vec<(String, String)> = fs::read_dir(HEADS_DIR)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    read_ref(entry.path()).map(|hash| (name, hash))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if !branches.iter().any(|(name, _)| name == DEFAULT_BRANCH)
+        && let Some(hash) = read_branch(DEFAULT_BRANCH)
+    {
+        branches.push((DEFAULT_BRANCH.to_string(), hash));
+    }
+
+    branches.sort();
+    branches
+}
+
+/// Moves a commit hash kept in HEAD by the pre-branch layout to the default
+/// branch's ref, before HEAD is rewritten.
+fn persist_legacy_head() -> Result<()> {
+    if current_branch().as_deref() == Some(DEFAULT_BRANCH)
+        && !Path::new(HEADS_DIR).join(DEFAULT_BRANCH).exists()
+        && let Some(hash) = read_branch(DEFAULT_BRANCH)
+    {
+        write_branch(DEFAULT_BRANCH, &hash)?;
+    }
+    Ok(())
+}
+
+/// Points HEAD at `name`, leaving any detached state.
+pub fn set_head_branch(name: &str) -> Result<()> {
+    persist_legacy_head()?;
+    write_ref(HEAD_PATH, &format!("{}{}", SYMREF_PREFIX, name))?;
+    clear_detached()
+}
+
+pub fn read_head() -> Option<String> {
+    match current_branch() {
+        Some(branch) => read_branch(&branch),
+        None => read_ref(HEAD_PATH),
+    }
+}
+
+/// Moves the current branch to `commit_hash`, or HEAD itself while detached.
+pub fn write_head(commit_hash: &str) -> Result<()> {
+    match current_branch() {
+        Some(branch) => {
+            write_branch(&branch, commit_hash)?;
+            write_ref(HEAD_PATH, &format!("{}{}", SYMREF_PREFIX, branch))
+        }
+        None => write_ref(HEAD_PATH, commit_hash),
+    }
+}
+
+/// Points HEAD directly at `commit_hash`, remembering the branch it left.
+pub fn detach_head(commit_hash: &str) -> Result<()> {
+    persist_legacy_head()?;
+    if let Some(branch) = current_branch() {
+        fs::write(DETACHED_PATH, branch)?;
+    }
+    write_ref(HEAD_PATH, commit_hash)
+}
+
+/// The branch HEAD pointed to before `envy checkout` detached it, if detached.
+pub fn read_detached() -> Option<String> {
+    read_ref(DETACHED_PATH)
+}
+
+pub fn clear_detached() -> Result<()> {
+    match fs::remove_file(DETACHED_PATH) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Refuses `action` while a commit is checked out without a branch, since it
+/// would build on or publish that commit instead of the branch.
+pub fn ensure_attached(action: &str) -> Result<()> {
+    if let Some(branch) = read_detached() {
+        let head = read_head().unwrap_or_default();
+        bail!(
+            "HEAD is detached at {}. Run `envy switch {}` to return to the branch before you {}.",
+            short_hash(&head, 8),
+            branch,
+            action
+        );
+    }
     Ok(())
 }
 
+fn remote_ref_path(branch: &str) -> std::path::PathBuf {
+    if branch == DEFAULT_BRANCH {
+        REMOTE_HEAD_PATH.into()
+    } else {
+        Path::new(REMOTE_REFS_DIR).join("heads").join(branch)
+    }
+}
+
+fn accepted_heads_path(branch: &str) -> std::path::PathBuf {
+    Path::new(ACCEPTED_DIR).join(branch)
+}
+
+/// Accepted heads of `branch`, oldest first. The default branch falls back to
+/// its log from the pre-branch layout until the next head is recorded.
+fn read_accepted(branch: &str) -> Vec<String> {
+    let path = accepted_heads_path(branch);
+    let path = if branch == DEFAULT_BRANCH && !path.exists() {
+        LEGACY_ACCEPTED_HEADS_PATH.into()
+    } else {
+        path
+    };
+
+    fs::read_to_string(path)
+        .map(|s| {
+            s.lines()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The last commit seen on the remote for `branch`.
+pub fn read_remote_ref(branch: &str) -> Option<String> {
+    read_ref(remote_ref_path(branch))
+}
+
+pub fn write_remote_ref(branch: &str, commit_hash: &str) -> Result<()> {
+    write_ref(remote_ref_path(branch), commit_hash)?;
+    record_accepted_head(branch, commit_hash)
+}
+
+pub fn delete_remote_ref(branch: &str) -> Result<()> {
+    let mut paths = vec![remote_ref_path(branch), accepted_heads_path(branch)];
+    if branch == DEFAULT_BRANCH {
+        paths.push(LEGACY_ACCEPTED_HEADS_PATH.into());
+    }
+    for path in paths {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// The remote ref of the upstream branch (`origin/HEAD` for the default branch).
 pub fn read_remote_head() -> Option<String> {
-    fs::read_to_string(REMOTE_HEAD_PATH)
-        .ok()
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty())
+    read_remote_ref(&upstream_branch())
 }
 
 pub fn write_remote_head(commit_hash: &str) -> Result<()> {
-    if let Some(parent) = Path::new(REMOTE_HEAD_PATH).parent() {
-        fs::create_dir_all(parent)?;
+    write_remote_ref(&upstream_branch(), commit_hash)
+}
+
+/// Every remote ref of the upstream branch this client has accepted, oldest first.
+pub fn read_accepted_heads() -> Vec<String> {
+    read_accepted(&upstream_branch())
+}
+
+fn record_accepted_head(branch: &str, commit_hash: &str) -> Result<()> {
+    let mut heads = read_accepted(branch);
+    if heads.last().map(String::as_str) == Some(commit_hash) {
+        return Ok(());
+    }
+
+    heads.push(commit_hash.to_string());
+    let keep_from = heads.len().saturating_sub(MAX_ACCEPTED_HEADS);
+    let mut contents = heads[keep_from..].join("\n");
+    contents.push('\n');
+    write_ref(accepted_heads_path(branch), &contents)?;
+
+    if branch == DEFAULT_BRANCH {
+        match fs::remove_file(LEGACY_ACCEPTED_HEADS_PATH) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
     }
-    fs::write(REMOTE_HEAD_PATH, commit_hash)?;
     Ok(())
 }
 
 pub fn save_commit(commit: &Commit) -> Result<String> {
     let manifest_key = get_project_key()?;
+    save_commit_with_key(commit, &manifest_key)
+}
 
+pub fn save_commit_with_key(commit: &Commit, manifest_key: &[u8]) -> Result<String> {
     let plaintext = serde_json::to_vec(commit)
         .map_err(|e| anyhow::anyhow!("Failed to serialize commit: {}", e))?;
 
-    let encrypted = encrypt_bytes_with_key(&plaintext, &manifest_key)?;
+    let project = load_project_config()?;
+    let aad = object_aad(&project.project_id, ObjectKind::Commit, "");
+    let encrypted = encrypt_bytes_with_key(&plaintext, manifest_key, &aad)?;
 
     let mut hasher = Sha256::new();
     hasher.update(&encrypted);
@@ -92,8 +348,9 @@ pub fn save_commit(commit: &Commit) -> Result<String> {
         .map_err(|e| anyhow::anyhow!("Failed to create commits directory: {}", e))?;
 
     let path = format!("{}/{}.blob", COMMITS_DIR, hash_hex);
-    fs::write(&path, encrypted)
-        .map_err(|e| anyhow::anyhow!("Failed to write commit {}: {}", &hash_hex[..8], e))?;
+    fs::write(&path, encrypted).map_err(|e| {
+        anyhow::anyhow!("Failed to write commit {}: {}", short_hash(&hash_hex, 8), e)
+    })?;
 
     Ok(hash_hex)
 }
@@ -106,6 +363,10 @@ pub fn save_commit_blob(commit_hash: &str, encrypted_data: &[u8]) -> Result<()>
 }
 
 pub fn load_commit(commit_hash: &str) -> Result<Commit> {
+    read_commit(commit_hash, true)
+}
+
+fn read_commit(commit_hash: &str, clear_session_on_failure: bool) -> Result<Commit> {
     let manifest_key = get_project_key()?;
 
     let path = format!("{}/{}.blob", COMMITS_DIR, commit_hash);
@@ -113,22 +374,46 @@ pub fn load_commit(commit_hash: &str) -> Result<Commit> {
     if !Path::new(&path).exists() {
         bail!(
             "Commit {} not found locally. Run `envy pull` to fetch it.",
-            &commit_hash[..8]
+            short_hash(commit_hash, 8)
         );
     }
 
-    let encrypted = fs::read(&path)
-        .map_err(|e| anyhow::anyhow!("Failed to read commit {}: {}", &commit_hash[..8], e))?;
-
-    let plaintext = decrypt_bytes_with_key(&encrypted, &manifest_key).map_err(|_| {
+    let encrypted = fs::read(&path).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to read commit {}: {}",
+            short_hash(commit_hash, 8),
+            e
+        )
+    })?;
+    verify_object_hash(&encrypted, commit_hash, "Cached commit").map_err(|e| {
         anyhow::anyhow!(
-            "Failed to decrypt commit {}. The passphrase may be incorrect.",
-            &commit_hash[..8]
+            "{}. Delete it and run `envy pull` to fetch a fresh copy.",
+            e
         )
     })?;
 
-    let commit: Commit = serde_json::from_slice(&plaintext)
-        .map_err(|e| anyhow::anyhow!("Failed to parse commit {}: {}", &commit_hash[..8], e))?;
+    let project = load_project_config()?;
+    let aad = object_aad(&project.project_id, ObjectKind::Commit, "");
+    let plaintext = match decrypt_bytes_with_key(&encrypted, &manifest_key, &aad) {
+        Ok(plain) => plain,
+        Err(_) => {
+            if clear_session_on_failure {
+                clear_session(&project.project_id)?;
+            }
+            bail!(
+                "Failed to decrypt commit {}. The passphrase may be incorrect.",
+                short_hash(commit_hash, 8)
+            );
+        }
+    };
+
+    let commit: Commit = serde_json::from_slice(&plaintext).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to parse commit {}: {}",
+            short_hash(commit_hash, 8),
+            e
+        )
+    })?;
 
     if commit.version != 1 {
         bail!(
@@ -145,6 +430,63 @@ pub fn commit_exists(commit_hash: &str) -> bool {
     Path::new(&path).exists()
 }
 
+pub fn resolve_commit(rev: &str) -> Result<String> {
+    let rev = rev.trim();
+
+    if rev.eq_ignore_ascii_case("HEAD") {
+        return read_head().ok_or_else(|| anyhow::anyhow!("No commits yet."));
+    }
+
+    if validate_branch_name(rev).is_ok()
+        && let Some(hash) = read_branch(rev)
+    {
+        return Ok(hash);
+    }
+
+    if let Some(hash) = resolve_tag(rev)? {
+        return Ok(hash);
+    }
+
+    // Anything else names a commit blob, so it must be a hash before it is
+    // used in a path.
+    if rev.len() < 4 || !rev.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("Unknown commit '{}'", rev);
+    }
+
+    let prefix = rev.to_ascii_lowercase();
+    if commit_exists(&prefix) {
+        return Ok(prefix);
+    }
+
+    let mut matches = Vec::new();
+
+    if let Ok(entries) = fs::read_dir(COMMITS_DIR) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if let Some(hash) = name.strip_suffix(".blob")
+                && hash.starts_with(&prefix)
+            {
+                matches.push(hash.to_string());
+            }
+        }
+    }
+
+    match matches.len() {
+        0 => bail!(
+            "Commit '{}' not found locally. Run `envy pull` to fetch it.",
+            rev
+        ),
+        1 => Ok(matches.remove(0)),
+        n => bail!("Commit prefix '{}' is ambiguous ({} matches)", rev, n),
+    }
+}
+
+pub fn load_commit_manifest(rev: &str) -> Result<Manifest> {
+    let commit_hash = resolve_commit(rev)?;
+    let commit = load_commit(&commit_hash)?;
+    load_manifest_by_hash(&commit.manifest_hash)
+}
+
 pub fn commit_blob_path(commit_hash: &str) -> std::path::PathBuf {
     Path::new(COMMITS_DIR).join(format!("{}.blob", commit_hash))
 }
@@ -170,19 +512,57 @@ pub fn walk_history(start_hash: &str, limit: Option<usize>) -> Result<Vec<(Strin
     Ok(history)
 }
 
+pub fn is_ancestor(ancestor: &str, descendant: &str) -> Result<bool> {
+    let mut current = Some(descendant.to_string());
+
+    while let Some(hash) = current {
+        if hash == ancestor {
+            return Ok(true);
+        }
+        current = load_commit(&hash)?.parent;
+    }
+
+    Ok(false)
+}
+
+/// Like `is_ancestor`, but treats history that cannot be loaded or decrypted as
+/// unrelated instead of failing, and leaves the session untouched.
+pub fn is_known_ancestor(ancestor: &str, descendant: &str) -> bool {
+    let mut current = Some(descendant.to_string());
+
+    while let Some(hash) = current {
+        if hash == ancestor {
+            return true;
+        }
+        current = match read_commit(&hash, false) {
+            Ok(commit) => commit.parent,
+            Err(_) => return false,
+        };
+    }
+
+    false
+}
+
+/// Commits on the current branch that the remote does not have yet, newest
+/// first. History below any remote ref (of any branch) is already uploaded.
 pub fn commits_ahead_of_remote() -> Result<Vec<String>> {
     let local_head = match read_head() {
         Some(h) => h,
         None => return Ok(vec![]),
     };
 
-    let remote_head = read_remote_head();
+    let mut remote_heads: std::collections::HashSet<String> =
+        read_remote_head().into_iter().collect();
+    if let Ok(entries) = fs::read_dir(Path::new(REMOTE_REFS_DIR).join("heads")) {
+        remote_heads.extend(entries.flatten().filter_map(|entry| read_ref(entry.path())));
+    }
+    remote_heads.extend(read_remote_ref(DEFAULT_BRANCH));
 
     let mut commits_to_push = Vec::new();
     let mut current = Some(local_head);
 
     while let Some(hash) = current {
-        if Some(&hash) == remote_head.as_ref() {
+        if remote_heads.contains(&hash) {
             break;
         }
 
@@ -231,3 +611,16 @@ pub fn get_head_manifest_hash() -> Option<String> {
     let commit = load_commit(&head).ok()?;
     Some(commit.manifest_hash)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_commit_rejects_paths() {
+        for rev in ["../../config", "../commits/abcd", "abcd/../ef01", "."] {
+            let err = resolve_commit(rev).unwrap_err().to_string();
+            assert!(err.starts_with("Unknown commit"), "{}: {}", rev, err);
+        }
+    }
+}
@@ -0,0 +1,469 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use anyhow::Result;
+
+pub fn parse_env(contents: &[u8], source: &str) -> Result<Vec<(String, String)>> {
+    let mut vars = Vec::new();
+
+    for item in dotenvy::from_read_iter(contents) {
+        let (key, value) =
+            item.map_err(|e| anyhow::anyhow!("Failed to parse '{}': {}", source, e))?;
+        vars.push((key, value));
+    }
+
+    Ok(vars)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyChange {
+    Added {
+        key: String,
+        value: String,
+    },
+    Removed {
+        key: String,
+        value: String,
+    },
+    Changed {
+        key: String,
+        old: String,
+        new: String,
+    },
+}
+
+impl KeyChange {
+    pub fn key(&self) -> &str {
+        match self {
+            KeyChange::Added { key, .. }
+            | KeyChange::Removed { key, .. }
+            | KeyChange::Changed { key, .. } => key,
+        }
+    }
+}
+
+/// Key-level changes from `old` to `new`, sorted by key. When a key appears
+/// more than once, the last value wins, as it would when loaded.
+pub fn diff_env(old: &[(String, String)], new: &[(String, String)]) -> Vec<KeyChange> {
+    let old: BTreeMap<&str, &str> = old.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+    let new: BTreeMap<&str, &str> = new.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+
+    let mut changes = Vec::new();
+
+    for (key, old_value) in &old {
+        match new.get(key) {
+            None => changes.push(KeyChange::Removed {
+                key: key.to_string(),
+                value: old_value.to_string(),
+            }),
+            Some(new_value) if new_value != old_value => changes.push(KeyChange::Changed {
+                key: key.to_string(),
+                old: old_value.to_string(),
+                new: new_value.to_string(),
+            }),
+            Some(_) => {}
+        }
+    }
+
+    for (key, value) in &new {
+        if !old.contains_key(key) {
+            changes.push(KeyChange::Added {
+                key: key.to_string(),
+                value: value.to_string(),
+            });
+        }
+    }
+
+    changes.sort_by(|a, b| a.key().cmp(b.key()));
+    changes
+}
+
+/// Where a key is assigned in a dotenv file, as byte ranges into its text.
+struct Assignment {
+    key: String,
+    /// Whole lines covered by the assignment, including multi-line quoted values.
+    lines: Range<usize>,
+    /// The raw value token, including quotes.
+    value: Range<usize>,
+    quote: Option<char>,
+}
+
+pub fn is_valid_key(key: &str) -> bool {
+    key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Length of `export KEY =` plus following blanks, if `line` starts an assignment.
+fn assignment_head(line: &str) -> Option<(String, usize)> {
+    let mut rest = line;
+
+    if let Some(after) = rest.strip_prefix("export")
+        && after.starts_with([' ', '\t'])
+    {
+        rest = after.trim_start_matches([' ', '\t']);
+    }
+
+    let key_len = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+        .unwrap_or(rest.len());
+    let key = &rest[..key_len];
+    if !is_valid_key(key) {
+        return None;
+    }
+
+    let after_key = rest[key_len..].trim_start_matches([' ', '\t']);
+    let after_equals = after_key.strip_prefix('=')?;
+    let value = after_equals.trim_start_matches([' ', '\t']);
+
+    Some((key.to_string(), line.len() - value.len()))
+}
+
+/// End of the value starting at `from`, following dotenvy: backslash escapes
+/// and quotes may appear anywhere in the value, quoted sections may span
+/// lines, and the value ends at unquoted whitespace or the end of the line.
+fn value_end(contents: &str, from: usize) -> usize {
+    let mut quote = None;
+    let mut escaped = false;
+
+    for (index, c) in contents[from..].char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+
+        match (quote, c) {
+            (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+            (Some('"'), '\\') | (None, '\\') => escaped = true,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, ' ' | '\t' | '\r' | '\n') => return from + index,
+            (None, _) => {}
+        }
+    }
+
+    contents.len()
+}
+
+fn line_end(contents: &str, from: usize) -> usize {
+    contents[from..]
+        .find('\n')
+        .map(|index| from + index + 1)
+        .unwrap_or(contents.len())
+}
+
+fn assignments(contents: &str) -> Vec<Assignment> {
+    let mut found = Vec::new();
+    let mut pos = 0;
+
+    while pos < contents.len() {
+        let end = line_end(contents, pos);
+        let line = contents[pos..end].trim_end_matches(['\r', '\n']);
+        let indent = line.len() - line.trim_start().len();
+
+        let Some((key, head_len)) = assignment_head(&line[indent..]) else {
+            pos = end;
+            continue;
+        };
+
+        let value_start = pos + indent + head_len;
+        let (value_end, quote) = match contents[value_start..].chars().next() {
+            Some('#') => (value_start, None),
+            first => (
+                value_end(contents, value_start),
+                first.filter(|c| matches!(c, '\'' | '"')),
+            ),
+        };
+
+        let lines_end = if value_end > value_start && contents[..value_end].ends_with('\n') {
+            value_end
+        } else {
+            line_end(contents, value_end)
+        };
+
+        found.push(Assignment {
+            key,
+            lines: pos..lines_end,
+            value: value_start..value_end,
+            quote,
+        });
+        pos = lines_end;
+    }
+
+    found
+}
+
+/// Formats `value` so dotenv parsing returns it unchanged, keeping the
+/// `preferred` quote style where it can represent the value.
+fn quote_value(value: &str, preferred: Option<char>) -> String {
+    let bare_safe = value
+        .chars()
+        .all(|c| !c.is_whitespace() && !matches!(c, '\'' | '"' | '\\' | '$' | '#' | '`'));
+    let single_safe = !value.contains(['\'', '\n', '\r']);
+
+    match preferred {
+        None if bare_safe => value.to_string(),
+        Some('"') => double_quote(value),
+        _ if single_safe => format!("'{}'", value),
+        _ => double_quote(value),
+    }
+}
+
+fn double_quote(value: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '$' => quoted.push_str("\\$"),
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Sets `key` to `value`, editing the last assignment of the key in place so
+/// comments, ordering and surrounding formatting are kept, or appending a new line.
+pub fn set_var(contents: &str, key: &str, value: &str) -> String {
+    match assignments(contents).into_iter().rfind(|a| a.key == key) {
+        Some(assignment) => {
+            let mut quoted = quote_value(value, assignment.quote);
+            if assignment.value.is_empty() && contents[assignment.value.end..].starts_with('#') {
+                quoted.push(' ');
+            }
+
+            format!(
+                "{}{}{}",
+                &contents[..assignment.value.start],
+                quoted,
+                &contents[assignment.value.end..]
+            )
+        }
+        None => {
+            let mut updated = contents.to_string();
+            if !updated.is_empty() && !updated.ends_with('\n') {
+                updated.push('\n');
+            }
+            updated.push_str(&format!("{}={}\n", key, quote_value(value, None)));
+            updated
+        }
+    }
+}
+
+/// Replaces every value with the placeholder `fill` returns for its key, keeping
+/// keys, comments and layout. A returned comment is added on the line above the
+/// assignment unless it is already there.
+pub fn template(contents: &str, fill: impl Fn(&str) -> (String, Option<String>)) -> String {
+    let mut out = String::with_capacity(contents.len());
+    let mut pos = 0;
+
+    for assignment in assignments(contents) {
+        let (placeholder, comment) = fill(&assignment.key);
+
+        out.push_str(&contents[pos..assignment.lines.start]);
+        if let Some(comment) = comment {
+            let line = format!("# {}\n", comment);
+            if !out.ends_with(&line) {
+                out.push_str(&line);
+            }
+        }
+
+        let mut quoted = quote_value(&placeholder, None);
+        if quoted.is_empty() && contents[assignment.value.end..].starts_with('#') {
+            quoted.push(' ');
+        }
+        let line = format!(
+            "{}{}{}",
+            &contents[assignment.lines.start..assignment.value.start],
+            quoted,
+            &contents[assignment.value.end..assignment.lines.end]
+        );
+
+        // Fail closed: unless the rewritten line reads back as just the
+        // placeholder, only the key is kept so no part of the value survives.
+        if parse_env(line.as_bytes(), "").ok() == Some(vec![(assignment.key.clone(), placeholder)])
+        {
+            out.push_str(&line);
+        } else {
+            out.push_str(&format!("{}={}\n", assignment.key, quoted.trim_end()));
+        }
+
+        pos = assignment.lines.end;
+    }
+
+    out.push_str(&contents[pos..]);
+    out
+}
+
+/// Formats variables as dotenv lines that parse back to the same values.
+pub fn to_dotenv(vars: &[(String, String)]) -> String {
+    vars.iter()
+        .map(|(key, value)| format!("{}={}\n", key, quote_value(value, None)))
+        .collect()
+}
+
+/// Removes every assignment of `key`. Returns `None` if the key was not present.
+pub fn unset_var(contents: &str, key: &str) -> Option<String> {
+    let matching: Vec<Range<usize>> = assignments(contents)
+        .into_iter()
+        .filter(|a| a.key == key)
+        .map(|a| a.lines)
+        .collect();
+
+    if matching.is_empty() {
+        return None;
+    }
+
+    let mut updated = String::with_capacity(contents.len());
+    let mut pos = 0;
+    for lines in matching {
+        updated.push_str(&contents[pos..lines.start]);
+        pos = lines.end;
+    }
+    updated.push_str(&contents[pos..]);
+
+    Some(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_env() {
+        let contents = b"# comment\nFOO=bar\nQUOTED=\"hello world\"\n\nEMPTY=\n";
+        let vars = parse_env(contents, ".env").unwrap();
+
+        assert_eq!(
+            vars,
+            vec![
+                ("FOO".to_string(), "bar".to_string()),
+                ("QUOTED".to_string(), "hello world".to_string()),
+                ("EMPTY".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_env() {
+        let old = vars(&[("A", "1"), ("B", "2"), ("C", "3"), ("C", "4")]);
+        let new = vars(&[("B", "2"), ("C", "5"), ("D", "6")]);
+
+        assert_eq!(
+            diff_env(&old, &new),
+            vec![
+                KeyChange::Removed {
+                    key: "A".to_string(),
+                    value: "1".to_string()
+                },
+                KeyChange::Changed {
+                    key: "C".to_string(),
+                    old: "4".to_string(),
+                    new: "5".to_string()
+                },
+                KeyChange::Added {
+                    key: "D".to_string(),
+                    value: "6".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_set_var_preserves_layout() {
+        let contents =
+            "# database\nexport DB_URL='postgres://old' # primary\nNAME=app\n\nQUOTED=\"a b\"\n";
+
+        let updated = set_var(contents, "DB_URL", "postgres://new");
+        assert_eq!(
+            updated,
+            "# database\nexport DB_URL='postgres://new' # primary\nNAME=app\n\nQUOTED=\"a b\"\n"
+        );
+
+        let updated = set_var(&updated, "QUOTED", "say \"hi\" $HOME");
+        assert!(updated.ends_with("QUOTED=\"say \\\"hi\\\" \\$HOME\"\n"));
+
+        let updated = set_var(&updated, "NEW", "two words");
+        assert!(updated.ends_with("NEW='two words'\n"));
+
+        let vars = parse_env(updated.as_bytes(), ".env").unwrap();
+        assert!(vars.contains(&("QUOTED".to_string(), "say \"hi\" $HOME".to_string())));
+        assert!(vars.contains(&("NEW".to_string(), "two words".to_string())));
+    }
+
+    #[test]
+    fn test_set_var_follows_dotenv_values() {
+        let contents = "A=a\\ secret # note\nB=abc\"def ghi\"\nC=x\n";
+
+        let updated = set_var(contents, "A", "new");
+        assert_eq!(updated, "A=new # note\nB=abc\"def ghi\"\nC=x\n");
+
+        let updated = set_var(&updated, "B", "new");
+        assert_eq!(
+            parse_env(updated.as_bytes(), ".env").unwrap(),
+            vec![
+                ("A".to_string(), "new".to_string()),
+                ("B".to_string(), "new".to_string()),
+                ("C".to_string(), "x".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_template_leaves_no_value() {
+        // E is not valid dotenv, so its span cannot be trusted and only the key is kept.
+        let contents =
+            "A=a\\ secret\nB=abc\"def ghi\"\nC='sec'ret # note\nD=\"x\\\"y\"\nE=open sesame\n";
+        let templated = template(contents, |_| (String::new(), None));
+
+        for leaked in [
+            "secret", "sec", "ret", "abc", "def", "ghi", "x", "y", "open", "sesame",
+        ] {
+            assert!(!templated.contains(leaked), "{:?}", templated);
+        }
+        assert_eq!(
+            parse_env(templated.as_bytes(), ".env.example").unwrap(),
+            vec![
+                ("A".to_string(), String::new()),
+                ("B".to_string(), String::new()),
+                ("C".to_string(), String::new()),
+                ("D".to_string(), String::new()),
+                ("E".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_template() {
+        let contents =
+            "# db\nDB_URL=\"postgres://secret\" # primary\nKEY='multi\nline'\nPORT=5432\n";
+
+        let templated = template(contents, |key| match key {
+            "PORT" => ("3000".to_string(), Some("HTTP port".to_string())),
+            _ => (String::new(), None),
+        });
+
+        assert_eq!(
+            templated,
+            "# db\nDB_URL= # primary\nKEY=\n# HTTP port\nPORT=3000\n"
+        );
+    }
+
+    #[test]
+    fn test_unset_var() {
+        let contents = "A=1\nB=\"multi\nline\"\n# keep\nC=3\nB=again\n";
+
+        assert_eq!(unset_var(contents, "B").unwrap(), "A=1\n# keep\nC=3\n");
+        assert!(unset_var(contents, "MISSING").is_none());
+    }
+}
@@ -0,0 +1,235 @@
+use anyhow::{Result, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use clap::ValueEnum;
+
+use super::dotenv::is_valid_key;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Json,
+    Yaml,
+    /// `export KEY='value'` lines for sourcing in a shell.
+    Shell,
+    /// `docker run --env-file`, which has no quoting or multiline values.
+    Docker,
+    /// systemd `EnvironmentFile=`.
+    Systemd,
+    /// A Kubernetes `Secret` manifest with base64-encoded data.
+    K8sSecret,
+    /// Terraform `.tfvars`.
+    Tfvars,
+}
+
+/// Keeps the last value of each key, in the order keys first appear.
+fn dedupe(vars: &[(String, String)]) -> Vec<(&str, &str)> {
+    let mut unique: Vec<(&str, &str)> = Vec::new();
+
+    for (key, value) in vars {
+        match unique.iter_mut().find(|(existing, _)| existing == key) {
+            Some(entry) => entry.1 = value,
+            None => unique.push((key, value)),
+        }
+    }
+
+    unique
+}
+
+fn json_string(value: &str) -> String {
+    serde_json::Value::String(value.to_string()).to_string()
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+fn systemd_quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '\\' | '"' | '$' | '`' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn hcl_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            // Template sequences would otherwise be interpolated.
+            '$' | '%' if chars.peek() == Some(&'{') => {
+                quoted.push(c);
+                quoted.push(c);
+            }
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Lowercases and replaces characters not allowed in a Kubernetes object name.
+pub fn k8s_name(name: &str) -> String {
+    let mut sanitized = String::new();
+    for c in name.to_ascii_lowercase().chars() {
+        if c.is_ascii_alphanumeric() {
+            sanitized.push(c);
+        } else if !sanitized.is_empty() && !sanitized.ends_with('-') {
+            sanitized.push('-');
+        }
+    }
+
+    let sanitized = sanitized.trim_end_matches('-');
+    if sanitized.is_empty() {
+        "envoy-secrets".to_string()
+    } else {
+        sanitized.chars().take(253).collect()
+    }
+}
+
+/// Renders parsed variables in `format`. `name` is used as the Kubernetes
+/// Secret's name and ignored by other formats.
+pub fn render(vars: &[(String, String)], format: ExportFormat, name: &str) -> Result<String> {
+    let vars = dedupe(vars);
+
+    if matches!(
+        format,
+        ExportFormat::Shell | ExportFormat::Docker | ExportFormat::Systemd | ExportFormat::Tfvars
+    ) && let Some((key, _)) = vars.iter().find(|(key, _)| !is_valid_key(key))
+    {
+        bail!("'{}' is not a valid variable name for this format", key);
+    }
+
+    let mut out = String::new();
+
+    match format {
+        ExportFormat::Json => {
+            out.push('{');
+            for (i, (key, value)) in vars.iter().enumerate() {
+                out.push_str(if i == 0 { "\n" } else { ",\n" });
+                out.push_str(&format!("  {}: {}", json_string(key), json_string(value)));
+            }
+            out.push_str(if vars.is_empty() { "}\n" } else { "\n}\n" });
+        }
+        ExportFormat::Yaml => {
+            // JSON strings are valid YAML double-quoted scalars.
+            if vars.is_empty() {
+                out.push_str("{}\n");
+            }
+            for (key, value) in &vars {
+                out.push_str(&format!("{}: {}\n", json_string(key), json_string(value)));
+            }
+        }
+        ExportFormat::Shell => {
+            for (key, value) in &vars {
+                out.push_str(&format!("export {}={}\n", key, shell_quote(value)));
+            }
+        }
+        ExportFormat::Docker => {
+            for (key, value) in &vars {
+                if value.contains(['\n', '\r']) {
+                    bail!(
+                        "'{}' has a multiline value, which Docker env files cannot represent",
+                        key
+                    );
+                }
+                out.push_str(&format!("{}={}\n", key, value));
+            }
+        }
+        ExportFormat::Systemd => {
+            for (key, value) in &vars {
+                out.push_str(&format!("{}={}\n", key, systemd_quote(value)));
+            }
+        }
+        ExportFormat::K8sSecret => {
+            out.push_str("apiVersion: v1\nkind: Secret\nmetadata:\n");
+            out.push_str(&format!("  name: {}\n", k8s_name(name)));
+            out.push_str("type: Opaque\n");
+            if vars.is_empty() {
+                out.push_str("data: {}\n");
+            } else {
+                out.push_str("data:\n");
+            }
+            for (key, value) in &vars {
+                out.push_str(&format!(
+                    "  {}: {}\n",
+                    json_string(key),
+                    STANDARD.encode(value.as_bytes())
+                ));
+            }
+        }
+        ExportFormat::Tfvars => {
+            for (key, value) in &vars {
+                out.push_str(&format!("{} = {}\n", key, hcl_string(value)));
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_render_quoting() {
+        let vars = vars(&[
+            ("A", "it's \"x\""),
+            ("B", "line1\nline2 ${HOME}"),
+            ("A", "last"),
+        ]);
+
+        assert_eq!(
+            render(&vars, ExportFormat::Shell, "").unwrap(),
+            "export A='last'\nexport B='line1\nline2 ${HOME}'\n"
+        );
+        assert_eq!(
+            render(&vars, ExportFormat::Json, "").unwrap(),
+            "{\n  \"A\": \"last\",\n  \"B\": \"line1\\nline2 ${HOME}\"\n}\n"
+        );
+        assert_eq!(
+            render(&vars, ExportFormat::Systemd, "").unwrap(),
+            "A=\"last\"\nB=\"line1\nline2 \\${HOME}\"\n"
+        );
+        assert_eq!(
+            render(&vars, ExportFormat::Tfvars, "").unwrap(),
+            "A = \"last\"\nB = \"line1\\nline2 $${HOME}\"\n"
+        );
+        assert!(render(&vars, ExportFormat::Docker, "").is_err());
+    }
+
+    #[test]
+    fn test_render_k8s_secret() {
+        let rendered = render(
+            &vars(&[("TOKEN", "abc")]),
+            ExportFormat::K8sSecret,
+            "My App",
+        )
+        .unwrap();
+
+        assert_eq!(
+            rendered,
+            "apiVersion: v1\nkind: Secret\nmetadata:\n  name: my-app\ntype: Opaque\ndata:\n  \"TOKEN\": YWJj\n"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use anyhow::{Result, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use clap::ValueEnum;
+use serde_norway::Value;
+
+use super::dotenv::{is_valid_key, parse_env, to_dotenv};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImportFormat {
+    Dotenv,
+    /// A flat JSON object.
+    Json,
+    /// A flat YAML mapping.
+    Yaml,
+    /// A Kubernetes `Secret` manifest; `data` is base64-decoded and `stringData` taken as is.
+    K8sSecret,
+    /// A `docker run --env-file` file, whose values are taken literally.
+    Docker,
+}
+
+fn scalar(key: &str, value: &Value, source: &str) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Bool(b) => Ok(b.to_string()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::Null => Ok(String::new()),
+        _ => bail!(
+            "'{}' in '{}' is not a string, number or boolean",
+            key,
+            source
+        ),
+    }
+}
+
+fn mapping_vars(value: &Value, source: &str) -> Result<Vec<(String, String)>> {
+    let Value::Mapping(map) = value else {
+        bail!("'{}' is not a map of variables", source);
+    };
+
+    map.iter()
+        .map(|(key, value)| {
+            let key = key
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("'{}' has a non-string key", source))?;
+            Ok((key.to_string(), scalar(key, value, source)?))
+        })
+        .collect()
+}
+
+fn k8s_secret_vars(document: &Value, source: &str) -> Result<Vec<(String, String)>> {
+    if document.get("kind").and_then(Value::as_str) != Some("Secret") {
+        bail!("'{}' is not a Kubernetes Secret", source);
+    }
+
+    let mut vars = Vec::new();
+
+    if let Some(data) = document.get("data").filter(|data| !data.is_null()) {
+        for (key, encoded) in mapping_vars(data, source)? {
+            let decoded = STANDARD
+                .decode(encoded.trim())
+                .map_err(|_| anyhow::anyhow!("'{}' in '{}' is not valid base64", key, source))?;
+            let value = String::from_utf8(decoded)
+                .map_err(|_| anyhow::anyhow!("'{}' in '{}' is not valid UTF-8", key, source))?;
+            vars.push((key, value));
+        }
+    }
+
+    // Kubernetes gives `stringData` precedence over `data`.
+    if let Some(string_data) = document.get("stringData").filter(|data| !data.is_null()) {
+        for (key, value) in mapping_vars(string_data, source)? {
+            vars.retain(|(existing, _)| *existing != key);
+            vars.push((key, value));
+        }
+    }
+
+    Ok(vars)
+}
+
+fn docker_vars(contents: &str, source: &str) -> Result<Vec<(String, String)>> {
+    let mut vars = Vec::new();
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            bail!(
+                "Line {} of '{}' has no value; Docker would read '{}' from the host environment",
+                number + 1,
+                source,
+                line.trim_end()
+            );
+        };
+        vars.push((key.trim().to_string(), value.to_string()));
+    }
+
+    Ok(vars)
+}
+
+/// Converts `contents` in `format` to dotenv text. Dotenv input is validated
+/// and kept as is so comments and layout survive.
+pub fn convert_to_dotenv(contents: &[u8], format: ImportFormat, source: &str) -> Result<String> {
+    let text = std::str::from_utf8(contents)
+        .map_err(|_| anyhow::anyhow!("'{}' is not valid UTF-8", source))?;
+
+    let vars = match format {
+        ImportFormat::Dotenv => {
+            parse_env(contents, source)?;
+            return Ok(text.to_string());
+        }
+        ImportFormat::Json => {
+            let value: Value = serde_json::from_str(text)
+                .map_err(|e| anyhow::anyhow!("Failed to parse '{}': {}", source, e))?;
+            mapping_vars(&value, source)?
+        }
+        ImportFormat::Yaml | ImportFormat::K8sSecret => {
+            let value: Value = serde_norway::from_str(text)
+                .map_err(|e| anyhow::anyhow!("Failed to parse '{}': {}", source, e))?;
+            if format == ImportFormat::K8sSecret {
+                k8s_secret_vars(&value, source)?
+            } else {
+                mapping_vars(&value, source)?
+            }
+        }
+        ImportFormat::Docker => docker_vars(text, source)?,
+    };
+
+    if let Some((key, _)) = vars.iter().find(|(key, _)| !is_valid_key(key)) {
+        bail!("'{}' in '{}' is not a valid variable name", key, source);
+    }
+
+    Ok(to_dotenv(&vars))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_to_dotenv() {
+        let json = br#"{"PORT": 8080, "DEBUG": true, "GREETING": "hello world"}"#;
+        assert_eq!(
+            convert_to_dotenv(json, ImportFormat::Json, "in.json").unwrap(),
+            "PORT=8080\nDEBUG=true\nGREETING='hello world'\n"
+        );
+
+        let secret = b"apiVersion: v1\nkind: Secret\ndata:\n  TOKEN: YWJj\n  MULTI: YQpi\nstringData:\n  TOKEN: override\n";
+        let converted = convert_to_dotenv(secret, ImportFormat::K8sSecret, "secret.yaml").unwrap();
+        let vars = parse_env(converted.as_bytes(), ".env").unwrap();
+        assert_eq!(
+            vars,
+            vec![
+                ("MULTI".to_string(), "a\nb".to_string()),
+                ("TOKEN".to_string(), "override".to_string()),
+            ]
+        );
+
+        let docker = b"# comment\nURL=postgres://u:p@h/db?x=1 # not a comment\n";
+        let converted = convert_to_dotenv(docker, ImportFormat::Docker, "env.list").unwrap();
+        let vars = parse_env(converted.as_bytes(), ".env").unwrap();
+        assert_eq!(vars[0].1, "postgres://u:p@h/db?x=1 # not a comment");
+
+        assert!(convert_to_dotenv(b"A:\n  nested: 1\n", ImportFormat::Yaml, "in.yaml").is_err());
+    }
+}
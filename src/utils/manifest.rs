@@ -1,10 +1,81 @@
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::Path};
+use std::{collections::BTreeMap, path::Path};
+
+pub const MANIFEST_VERSION: u8 = 2;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Manifest {
     pub version: u8,
-    pub files: HashMap<String, String>,
+    pub files: BTreeMap<String, FileEntry>,
+}
+
+/// How a tracked file's blob is encrypted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scheme {
+    /// Encrypted with a key derived from a per-file passphrase.
+    Passphrase,
+    /// Encrypted with a random data key wrapped by the manifest key.
+    ProjectKey,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileEntry {
+    pub blob: String,
+    pub scheme: Scheme,
+    /// Data key wrapped with the manifest key, for `project-key` entries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// HMAC-SHA256 of the plaintext under a key derived from the manifest key.
+    /// Missing for entries migrated from a v1 manifest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+    /// Unix permission bits of the original file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    /// Plaintext size in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+/// Version 1 layout: path to blob hash, with wrapped data keys kept alongside.
+#[derive(Deserialize)]
+struct ManifestV1 {
+    files: BTreeMap<String, String>,
+    #[serde(default)]
+    keys: BTreeMap<String, String>,
+}
+
+impl From<ManifestV1> for Manifest {
+    fn from(v1: ManifestV1) -> Self {
+        let mut keys = v1.keys;
+        let files = v1
+            .files
+            .into_iter()
+            .map(|(path, blob)| {
+                let key = keys.remove(&path);
+                let scheme = if key.is_some() {
+                    Scheme::ProjectKey
+                } else {
+                    Scheme::Passphrase
+                };
+                let entry = FileEntry {
+                    blob,
+                    scheme,
+                    key,
+                    digest: None,
+                    mode: None,
+                    size: None,
+                };
+                (path, entry)
+            })
+            .collect();
+
+        Self {
+            version: MANIFEST_VERSION,
+            files,
+        }
+    }
 }
 
 impl Default for Manifest {
@@ -16,17 +87,43 @@ impl Default for Manifest {
 impl Manifest {
     pub fn new() -> Self {
         Self {
-            version: 1,
-            files: HashMap::new(),
+            version: MANIFEST_VERSION,
+            files: BTreeMap::new(),
         }
     }
 }
+
+/// Parses a decrypted manifest, upgrading version 1 manifests in memory.
+pub fn parse_manifest(plaintext: &[u8]) -> Result<Manifest> {
+    #[derive(Deserialize)]
+    struct Versioned {
+        version: u8,
+    }
+
+    let Versioned { version } = serde_json::from_slice(plaintext)
+        .map_err(|e| anyhow::anyhow!("Failed to parse manifest: {}", e))?;
+
+    match version {
+        1 => {
+            let v1: ManifestV1 = serde_json::from_slice(plaintext)
+                .map_err(|e| anyhow::anyhow!("Failed to parse manifest: {}", e))?;
+            Ok(v1.into())
+        }
+        MANIFEST_VERSION => serde_json::from_slice(plaintext)
+            .map_err(|e| anyhow::anyhow!("Failed to parse manifest: {}", e)),
+        _ => bail!(
+            "Unsupported manifest version {}. Please update envy.",
+            version
+        ),
+    }
+}
 use crate::{
-    commands::crypto::{decrypt_bytes_with_key, encrypt_bytes_with_key},
+    commands::crypto::{ObjectKind, decrypt_bytes_with_key, encrypt_bytes_with_key, object_aad},
     utils::{
         project_config::load_project_config,
         session::{clear_session, derive_manifest_key_from_passphrase, load_session, save_session},
-        ui::{print_error, prompt_passphrase},
+        storage::verify_object_hash,
+        ui::{print_error, prompt_passphrase, short_hash},
     },
 };
 use anyhow::{Result, bail};
@@ -35,12 +132,22 @@ use sha2::{Digest, Sha256};
 use std::fs;
 
 pub fn save_manifest(manifest: &Manifest) -> Result<String> {
-    let plaintext = serde_json::to_vec(manifest)
-        .map_err(|e| anyhow::anyhow!("Failed to serialize manifest: {}", e))?;
-
     let manifest_key = get_project_key()?;
 
-    let encrypted = encrypt_bytes_with_key(&plaintext, &manifest_key)?;
+    let hash_hex = save_manifest_with_key(manifest, &manifest_key)?;
+
+    fs::write(".envoy/latest", &hash_hex)
+        .map_err(|e| anyhow::anyhow!("Failed to update latest manifest reference: {}", e))?;
+
+    Ok(hash_hex)
+}
+
+pub fn save_manifest_with_key(manifest: &Manifest, manifest_key: &[u8]) -> Result<String> {
+    let plaintext = canonical_json(manifest)?;
+
+    let project = load_project_config()?;
+    let aad = object_aad(&project.project_id, ObjectKind::Manifest, "");
+    let encrypted = encrypt_bytes_with_key(&plaintext, manifest_key, &aad)?;
 
     let mut hasher = Sha256::new();
     hasher.update(&encrypted);
@@ -50,15 +157,11 @@ pub fn save_manifest(manifest: &Manifest) -> Result<String> {
     fs::write(&path, encrypted)
         .map_err(|e| anyhow::anyhow!("Failed to write manifest blob: {}", e))?;
 
-    fs::write(".envoy/latest", &hash_hex)
-        .map_err(|e| anyhow::anyhow!("Failed to update latest manifest reference: {}", e))?;
-
     Ok(hash_hex)
 }
 
 pub fn load_manifest() -> Result<Manifest> {
     let project = load_project_config()?;
-    let manifest_key = get_project_key()?;
     if !std::path::Path::new(".envoy/latest").exists() {
         return Ok(Manifest::new());
     }
@@ -73,8 +176,16 @@ pub fn load_manifest() -> Result<Manifest> {
 
     let encrypted =
         fs::read(&path).map_err(|e| anyhow::anyhow!("Failed to read manifest blob: {}", e))?;
+    verify_object_hash(&encrypted, hash.trim(), "Cached manifest").map_err(|e| {
+        anyhow::anyhow!(
+            "{}. Delete it and run `envy pull` to fetch a fresh copy.",
+            e
+        )
+    })?;
 
-    let plaintext = match decrypt_bytes_with_key(&encrypted, &manifest_key) {
+    let manifest_key = get_project_key()?;
+    let aad = object_aad(&project.project_id, ObjectKind::Manifest, "");
+    let plaintext = match decrypt_bytes_with_key(&encrypted, &manifest_key, &aad) {
         Ok(plain) => plain,
         Err(_) => {
             clear_session(&project.project_id)?;
@@ -82,17 +193,7 @@ pub fn load_manifest() -> Result<Manifest> {
         }
     };
 
-    let manifest: Manifest = serde_json::from_slice(&plaintext)
-        .map_err(|e| anyhow::anyhow!("Failed to parse manifest: {}", e))?;
-
-    if manifest.version != 1 {
-        bail!(
-            "Unsupported manifest version {}. Please update envy.",
-            manifest.version
-        );
-    }
-
-    Ok(manifest)
+    parse_manifest(&plaintext)
 }
 
 pub fn load_manifest_by_hash(hash: &str) -> Result<Manifest> {
@@ -104,35 +205,38 @@ pub fn load_manifest_by_hash(hash: &str) -> Result<Manifest> {
     if !std::path::Path::new(&path).exists() {
         bail!(
             "Manifest blob {} not found in cache. Run `envy pull` to fetch it.",
-            &hash[..12]
+            short_hash(hash, 12)
         );
     }
 
-    let encrypted = fs::read(&path)
-        .map_err(|e| anyhow::anyhow!("Failed to read manifest blob {}: {}", &hash[..12], e))?;
-
-    let plaintext = match decrypt_bytes_with_key(&encrypted, &manifest_key) {
+    let encrypted = fs::read(&path).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to read manifest blob {}: {}",
+            short_hash(hash, 12),
+            e
+        )
+    })?;
+    verify_object_hash(&encrypted, hash.trim(), "Cached manifest").map_err(|e| {
+        anyhow::anyhow!(
+            "{}. Delete it and run `envy pull` to fetch a fresh copy.",
+            e
+        )
+    })?;
+
+    let aad = object_aad(&project.project_id, ObjectKind::Manifest, "");
+    let plaintext = match decrypt_bytes_with_key(&encrypted, &manifest_key, &aad) {
         Ok(plain) => plain,
         Err(_) => {
             clear_session(&project.project_id)?;
             bail!(
                 "Failed to decrypt manifest {}. The passphrase may be incorrect.",
-                &hash[..12]
+                short_hash(hash, 12)
             );
         }
     };
 
-    let manifest: Manifest = serde_json::from_slice(&plaintext)
-        .map_err(|e| anyhow::anyhow!("Failed to parse manifest {}: {}", &hash[..12], e))?;
-
-    if manifest.version != 1 {
-        bail!(
-            "Unsupported manifest version {}. Please update envy.",
-            manifest.version
-        );
-    }
-
-    Ok(manifest)
+    parse_manifest(&plaintext)
+        .map_err(|e| anyhow::anyhow!("Manifest {}: {}", short_hash(hash, 12), e))
 }
 
 const APPLIED_PATH: &str = ".envoy/cache/applied";
@@ -164,13 +268,36 @@ pub fn get_current_manifest_hash() -> Option<String> {
         .filter(|s| !s.is_empty())
 }
 
+/// Serializes a manifest as compact JSON with object keys sorted at every level,
+/// so the same content always produces the same bytes regardless of field order
+/// or the client version that wrote it.
+pub fn canonical_json(manifest: &Manifest) -> Result<Vec<u8>> {
+    let value = serde_json::to_value(manifest)
+        .map_err(|e| anyhow::anyhow!("Failed to serialize manifest: {}", e))?;
+
+    serde_json::to_vec(&value).map_err(|e| anyhow::anyhow!("Failed to serialize manifest: {}", e))
+}
+
 pub fn compute_manifest_content_hash(manifest: &Manifest) -> String {
-    let plaintext = serde_json::to_vec(manifest).unwrap_or_default();
+    let plaintext = canonical_json(manifest).unwrap_or_default();
     let mut hasher = Sha256::new();
     hasher.update(&plaintext);
     hex::encode(hasher.finalize())
 }
 
+/// The project key, fetched on first use by checks that may not need it.
+#[derive(Default)]
+pub struct LazyProjectKey(Option<Vec<u8>>);
+
+impl LazyProjectKey {
+    pub fn get(&mut self) -> Result<&[u8]> {
+        if self.0.is_none() {
+            self.0 = Some(get_project_key()?);
+        }
+        Ok(self.0.as_deref().unwrap_or_default())
+    }
+}
+
 pub fn get_project_key() -> Result<Vec<u8>> {
     use crate::utils::session::take_passphrase_override;
 
@@ -190,10 +317,70 @@ pub fn get_project_key() -> Result<Vec<u8>> {
                     }
                 }
             };
-            println!();
+            eprintln!();
             derive_manifest_key_from_passphrase(&passphrase, &project.project_id)?
         }
     };
     save_session(&project.project_id, &manifest_key)?;
     Ok(manifest_key)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(blob: &str) -> FileEntry {
+        FileEntry {
+            blob: blob.to_string(),
+            scheme: Scheme::Passphrase,
+            key: None,
+            digest: None,
+            mode: None,
+            size: None,
+        }
+    }
+
+    #[test]
+    fn test_content_hash_ignores_insertion_order() {
+        let mut first = Manifest::new();
+        let mut second = Manifest::new();
+
+        for (path, hash) in [(".env", "aa"), (".env.local", "bb"), ("api/.env", "cc")] {
+            first.files.insert(path.to_string(), entry(hash));
+        }
+        for (path, hash) in [("api/.env", "cc"), (".env", "aa"), (".env.local", "bb")] {
+            second.files.insert(path.to_string(), entry(hash));
+        }
+
+        assert_eq!(
+            compute_manifest_content_hash(&first),
+            compute_manifest_content_hash(&second)
+        );
+    }
+
+    #[test]
+    fn test_canonical_json_sorts_keys() {
+        let manifest = parse_manifest(
+            br#"{"version":2,"files":{"b":{"scheme":"passphrase","blob":"2"},"a":{"size":3,"blob":"1","scheme":"project-key","key":"k"}}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            canonical_json(&manifest).unwrap(),
+            br#"{"files":{"a":{"blob":"1","key":"k","scheme":"project-key","size":3},"b":{"blob":"2","scheme":"passphrase"}},"version":2}"#
+        );
+    }
+
+    #[test]
+    fn test_parse_v1_manifest() {
+        let manifest = parse_manifest(
+            br#"{"version":1,"files":{".env":"aa","api/.env":"bb"},"keys":{"api/.env":"kk"}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.version, MANIFEST_VERSION);
+        assert_eq!(manifest.files[".env"], entry("aa"));
+        assert_eq!(manifest.files["api/.env"].scheme, Scheme::ProjectKey);
+        assert_eq!(manifest.files["api/.env"].key.as_deref(), Some("kk"));
+    }
+}
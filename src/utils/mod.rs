@@ -1,10 +1,17 @@
 pub mod commit;
 pub mod config;
+pub mod dotenv;
+pub mod export;
+pub mod import;
 pub mod initialized;
 pub mod manifest;
 pub mod members;
 pub mod paths;
+pub mod profile;
 pub mod project_config;
+pub mod schema;
 pub mod session;
 pub mod storage;
+pub mod tag;
 pub mod ui;
+pub mod worktree;
@@ -13,7 +13,10 @@ pub fn normalize_path(path: &str) -> String {
 
     let normalized = normalized.trim_start_matches('/');
 
-    let parts: Vec<&str> = normalized.split('/').filter(|p| !p.is_empty()).collect();
+    let parts: Vec<&str> = normalized
+        .split('/')
+        .filter(|p| !p.is_empty() && *p != ".")
+        .collect();
 
     parts.join("/")
 }
@@ -32,6 +35,39 @@ pub fn ensure_parent_exists(path: &Path) -> std::io::Result<()> {
     Ok(())
 }
 
+/// Unix permission bits of a file, if the platform has them.
+pub fn file_mode(path: &Path) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::metadata(path)
+            .ok()
+            .map(|metadata| metadata.permissions().mode() & 0o777)
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+pub fn set_file_mode(path: &Path, mode: u32) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o777))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = (path, mode);
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
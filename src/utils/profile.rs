@@ -0,0 +1,127 @@
+use anyhow::{Result, bail};
+use globset::GlobSet;
+
+use super::manifest::{FileEntry, Manifest};
+use super::paths::normalize_path;
+use super::project_config::ProjectConfig;
+use super::worktree::build_globset;
+
+/// A named environment selected with `--env`: the tracked files that belong to
+/// it and where they are restored to. Profiles have no key of their own;
+/// project-key files are readable by every member.
+pub struct Profile {
+    pub name: String,
+    files: GlobSet,
+    restore: Vec<(String, String)>,
+    passphrase_env: Option<String>,
+}
+
+impl Profile {
+    pub fn load(project: &ProjectConfig, name: &str) -> Result<Self> {
+        let Some(config) = project.profiles.get(name) else {
+            let known: Vec<&str> = project.profiles.keys().map(String::as_str).collect();
+            if known.is_empty() {
+                bail!(
+                    "Unknown environment '{}'. No profiles are defined in .envoy/config.toml.",
+                    name
+                );
+            }
+            bail!(
+                "Unknown environment '{}'. Defined profiles: {}",
+                name,
+                known.join(", ")
+            );
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            files: build_globset(
+                &config
+                    .files
+                    .iter()
+                    .map(|pattern| normalize_path(pattern))
+                    .collect::<Vec<_>>(),
+            )?,
+            restore: config
+                .restore
+                .iter()
+                .map(|(from, to)| (normalize_path(from), normalize_path(to)))
+                .collect(),
+            passphrase_env: config.passphrase_env.clone(),
+        })
+    }
+
+    /// Whether a tracked path belongs to the profile. Unlike tracking rules,
+    /// patterns match the whole path, so `.env.staging` selects only the file
+    /// at the root and `**/.env.staging` is needed to select it everywhere.
+    pub fn includes(&self, path: &str) -> bool {
+        self.files.is_match(normalize_path(path))
+    }
+
+    /// Tracked files in the profile, in path order.
+    pub fn select<'a>(&self, manifest: &'a Manifest) -> Vec<(&'a String, &'a FileEntry)> {
+        manifest
+            .files
+            .iter()
+            .filter(|(path, _)| self.includes(path))
+            .collect()
+    }
+
+    /// Working-tree path a tracked file is restored to.
+    pub fn target(&self, path: &str) -> String {
+        self.restore
+            .iter()
+            .find(|(from, _)| from == path)
+            .map(|(_, to)| to.clone())
+            .unwrap_or_else(|| path.to_string())
+    }
+
+    /// Passphrase for the profile's passphrase-mode files, if supplied through the environment.
+    pub fn file_passphrase(&self) -> Option<String> {
+        let var = self.passphrase_env.as_ref()?;
+        std::env::var(var).ok().filter(|value| !value.is_empty())
+    }
+}
+
+pub fn load_profile(project: &ProjectConfig, name: Option<&str>) -> Result<Option<Profile>> {
+    name.map(|name| Profile::load(project, name)).transpose()
+}
+
+/// Working-tree paths that some profile restores a differently named tracked file into.
+pub fn restore_targets(project: &ProjectConfig) -> Vec<String> {
+    project
+        .profiles
+        .values()
+        .flat_map(|profile| profile.restore.iter())
+        .filter(|(from, to)| normalize_path(from) != normalize_path(to))
+        .map(|(_, to)| normalize_path(to))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(files: &[&str]) -> Profile {
+        Profile {
+            name: "staging".to_string(),
+            files: build_globset(&files.iter().map(|f| f.to_string()).collect::<Vec<_>>()).unwrap(),
+            restore: Vec::new(),
+            passphrase_env: None,
+        }
+    }
+
+    #[test]
+    fn test_includes_matches_full_path() {
+        let root = profile(&[".env.staging", "api/.env.staging"]);
+        assert!(root.includes(".env.staging"));
+        assert!(root.includes("./api/.env.staging"));
+        assert!(!root.includes("billing/.env.staging"));
+        assert!(!root.includes("prod/api/.env.staging"));
+
+        let anywhere = profile(&["**/.env.staging"]);
+        assert!(anywhere.includes(".env.staging"));
+        assert!(anywhere.includes("billing/.env.staging"));
+        assert!(!anywhere.includes("billing/.env.prod"));
+    }
+}
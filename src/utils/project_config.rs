@@ -1,5 +1,8 @@
 use anyhow::{Result, bail};
 use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::commands::crypto::KdfParams;
 
 #[derive(Debug, Deserialize)]
 pub struct ProjectConfig {
@@ -8,11 +11,94 @@ pub struct ProjectConfig {
     pub name: Option<String>,
     pub remotes: std::collections::HashMap<String, String>,
     pub default_remote: String,
+    #[serde(default)]
+    pub crypto: CryptoConfig,
+    #[serde(default)]
+    pub tracking: TrackingConfig,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileConfig>,
+}
+
+/// Key derivation settings for newly encrypted files (`[crypto]` in `.envoy/config.toml`).
+/// Existing blobs keep the parameters recorded in their headers.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct CryptoConfig {
+    pub kdf: String,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for CryptoConfig {
+    fn default() -> Self {
+        Self {
+            kdf: "argon2id".to_string(),
+            memory_kib: KdfParams::LEGACY.memory_kib,
+            iterations: KdfParams::LEGACY.iterations,
+            parallelism: KdfParams::LEGACY.parallelism,
+        }
+    }
+}
+
+impl CryptoConfig {
+    pub fn kdf_params(&self) -> Result<KdfParams> {
+        if !self.kdf.eq_ignore_ascii_case("argon2id") {
+            bail!("Unsupported key derivation function '{}'", self.kdf);
+        }
+
+        let params = KdfParams {
+            memory_kib: self.memory_kib,
+            iterations: self.iterations,
+            parallelism: self.parallelism,
+        };
+        params.validate()?;
+
+        Ok(params)
+    }
+}
+
+/// Files that should be tracked (`[tracking]` in `.envoy/config.toml`). Paths
+/// listed in `.envoyignore` are excluded as well.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct TrackingConfig {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl Default for TrackingConfig {
+    fn default() -> Self {
+        Self {
+            include: vec![".env*".to_string()],
+            exclude: vec![
+                "*.example".to_string(),
+                "*.sample".to_string(),
+                "*.template".to_string(),
+            ],
+        }
+    }
+}
+
+/// A named environment (`[profiles.<name>]` in `.envoy/config.toml`).
+#[derive(Debug, Deserialize)]
+pub struct ProfileConfig {
+    /// Tracked paths or glob patterns that belong to the profile.
+    pub files: Vec<String>,
+    /// Where tracked paths are written on restore, e.g. `"env/staging/.env" = ".env"`.
+    #[serde(default)]
+    pub restore: BTreeMap<String, String>,
+    /// Environment variable holding the passphrase for the profile's passphrase-mode files.
+    pub passphrase_env: Option<String>,
 }
 
 pub fn load_project_config() -> Result<ProjectConfig> {
     let contents = std::fs::read_to_string(".envoy/config.toml")?;
-    let config: ProjectConfig = toml::from_str(&contents)?;
+    parse_project_config(&contents)
+}
+
+fn parse_project_config(contents: &str) -> Result<ProjectConfig> {
+    let config: ProjectConfig = toml::from_str(contents)?;
 
     if config.version != 1 {
         bail!("Unsupported project config version {}", config.version);
@@ -22,6 +108,11 @@ pub fn load_project_config() -> Result<ProjectConfig> {
         bail!("Default remote '{}' not defined", config.default_remote);
     }
 
+    config
+        .crypto
+        .kdf_params()
+        .map_err(|e| anyhow::anyhow!("Invalid [crypto] in .envoy/config.toml: {}", e))?;
+
     Ok(config)
 }
 
@@ -33,3 +124,30 @@ pub fn get_remote_url(config: &ProjectConfig, name: Option<&str>) -> Result<Stri
         .cloned()
         .ok_or_else(|| anyhow::anyhow!("Remote '{}' not found", remote))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = r#"
+        project_id = "project"
+        version = 1
+        default_remote = "origin"
+
+        [remotes]
+        origin = "http://localhost"
+    "#;
+
+    #[test]
+    fn test_crypto_bounds_checked_on_load() {
+        let config = parse_project_config(&format!("{}\n[crypto]\niterations = 3\n", BASE));
+        assert_eq!(config.unwrap().crypto.kdf_params().unwrap().iterations, 3);
+
+        let config = parse_project_config(&format!("{}\n[crypto]\niterations = 100\n", BASE));
+        let err = config.unwrap_err().to_string();
+        assert!(err.contains("[crypto]"), "{}", err);
+
+        let config = parse_project_config(&format!("{}\n[crypto]\nmemory_kib = 99999999\n", BASE));
+        assert!(config.is_err());
+    }
+}
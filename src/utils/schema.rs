@@ -0,0 +1,300 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use regex::Regex;
+use serde::Deserialize;
+
+use super::profile::Profile;
+use super::project_config::{ProfileConfig, ProjectConfig, load_project_config};
+use super::worktree::{build_globset, matches_path};
+
+pub const SCHEMA_PATH: &str = ".envoy/schema.toml";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyType {
+    #[default]
+    String,
+    Int,
+    Bool,
+    Url,
+    Enum,
+    Regex,
+}
+
+/// Rules for one key (`KEY = { type = "int", required = true }`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeySpec {
+    #[serde(rename = "type", default)]
+    pub kind: KeyType,
+    #[serde(default)]
+    pub required: bool,
+    pub default: Option<String>,
+    pub description: Option<String>,
+    /// Allowed values for `enum`.
+    #[serde(default)]
+    pub values: Vec<String>,
+    /// Pattern the whole value must match for `regex`.
+    pub pattern: Option<String>,
+}
+
+/// `.envoy/schema.toml`: key rules per tracked path (or glob) and per profile.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Schema {
+    #[serde(default)]
+    pub files: BTreeMap<String, BTreeMap<String, KeySpec>>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, BTreeMap<String, KeySpec>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub key: String,
+    pub message: String,
+}
+
+impl KeySpec {
+    fn regex(&self) -> Result<Option<Regex>> {
+        match (&self.kind, &self.pattern) {
+            (KeyType::Regex, Some(pattern)) => Regex::new(&format!("^(?:{})$", pattern))
+                .map(Some)
+                .map_err(|e| anyhow::anyhow!("Invalid pattern '{}': {}", pattern, e)),
+            (KeyType::Regex, None) => bail!("type \"regex\" needs a `pattern`"),
+            _ => Ok(None),
+        }
+    }
+
+    /// Describes why `value` does not satisfy the rule. The value itself is
+    /// never included, since it is usually a secret.
+    pub fn check_value(&self, value: &str) -> Option<String> {
+        match self.kind {
+            KeyType::String => None,
+            KeyType::Int => value
+                .parse::<i64>()
+                .is_err()
+                .then(|| "expected an integer".to_string()),
+            KeyType::Bool => (!matches!(
+                value.to_ascii_lowercase().as_str(),
+                "true" | "false" | "1" | "0" | "yes" | "no"
+            ))
+            .then(|| "expected a boolean (true/false)".to_string()),
+            KeyType::Url => url::Url::parse(value)
+                .is_err()
+                .then(|| "expected a URL".to_string()),
+            KeyType::Enum => (!self.values.iter().any(|allowed| allowed == value))
+                .then(|| format!("expected one of {}", self.values.join(", "))),
+            KeyType::Regex => {
+                let matches = self
+                    .regex()
+                    .ok()
+                    .flatten()
+                    .is_some_and(|regex| regex.is_match(value));
+                (!matches).then(|| {
+                    format!(
+                        "does not match pattern {}",
+                        self.pattern.as_deref().unwrap_or_default()
+                    )
+                })
+            }
+        }
+    }
+
+    fn verify(&self) -> Result<()> {
+        self.regex()?;
+
+        if self.kind == KeyType::Enum && self.values.is_empty() {
+            bail!("type \"enum\" needs `values`");
+        }
+        if let Some(default) = &self.default
+            && let Some(problem) = self.check_value(default)
+        {
+            bail!("default {}", problem);
+        }
+
+        Ok(())
+    }
+}
+
+impl Schema {
+    fn verify(&self) -> Result<()> {
+        let sections = self
+            .files
+            .iter()
+            .map(|(name, keys)| (format!("files.\"{}\"", name), keys))
+            .chain(
+                self.profiles
+                    .iter()
+                    .map(|(name, keys)| (format!("profiles.{}", name), keys)),
+            );
+
+        for (section, keys) in sections {
+            for (key, spec) in keys {
+                spec.verify()
+                    .map_err(|e| anyhow::anyhow!("{} in [{}]: {}", key, section, e))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that every `[profiles.<name>]` section names a profile defined
+    /// in `.envoy/config.toml`.
+    fn verify_profiles(&self, defined: &BTreeMap<String, ProfileConfig>) -> Result<()> {
+        if let Some(name) = self
+            .profiles
+            .keys()
+            .find(|name| !defined.contains_key(*name))
+        {
+            bail!(
+                "[profiles.{}] names a profile that is not defined in .envoy/config.toml",
+                name
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Rules that apply to a tracked path: those of every profile including it,
+    /// then those of matching `[files]` entries, which take precedence.
+    pub fn rules_for(
+        &self,
+        path: &str,
+        project: &ProjectConfig,
+    ) -> Result<BTreeMap<String, KeySpec>> {
+        let mut rules = BTreeMap::new();
+
+        for (name, keys) in &self.profiles {
+            if Profile::load(project, name)?.includes(path) {
+                rules.extend(keys.iter().map(|(k, v)| (k.clone(), v.clone())));
+            }
+        }
+
+        for (pattern, keys) in &self.files {
+            if matches_path(&build_globset(std::slice::from_ref(pattern))?, path) {
+                rules.extend(keys.iter().map(|(k, v)| (k.clone(), v.clone())));
+            }
+        }
+
+        Ok(rules)
+    }
+}
+
+/// Loads `.envoy/schema.toml`, or `None` if the project has no schema.
+pub fn load_schema() -> Result<Option<Schema>> {
+    let path = Path::new(SCHEMA_PATH);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", SCHEMA_PATH, e))?;
+    let schema: Schema = toml::from_str(&contents)
+        .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", SCHEMA_PATH, e))?;
+    schema
+        .verify()
+        .and_then(|_| schema.verify_profiles(&load_project_config()?.profiles))
+        .map_err(|e| anyhow::anyhow!("Invalid {}: {}", SCHEMA_PATH, e))?;
+
+    Ok(Some(schema))
+}
+
+/// Checks parsed variables against `rules`. The last assignment of a key wins.
+pub fn check(vars: &[(String, String)], rules: &BTreeMap<String, KeySpec>) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    for (key, spec) in rules {
+        match vars.iter().rfind(|(name, _)| name == key) {
+            None if spec.required && spec.default.is_none() => issues.push(Issue {
+                key: key.clone(),
+                message: "required but missing".to_string(),
+            }),
+            None => {}
+            Some((_, value)) => {
+                if let Some(problem) = spec.check_value(value) {
+                    issues.push(Issue {
+                        key: key.clone(),
+                        message: problem,
+                    });
+                }
+            }
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let schema: Schema = toml::from_str(
+            r#"
+            [files.".env"]
+            DATABASE_URL = { type = "url", required = true }
+            PORT = { type = "int", default = "3000" }
+            LOG_LEVEL = { type = "enum", values = ["debug", "info"] }
+            API_KEY = { type = "regex", pattern = "sk_[a-z]+", required = true }
+            "#,
+        )
+        .unwrap();
+        schema.verify().unwrap();
+
+        let rules = &schema.files[".env"];
+        let vars = vec![
+            ("PORT".to_string(), "abc".to_string()),
+            ("LOG_LEVEL".to_string(), "trace".to_string()),
+            ("API_KEY".to_string(), "sk_live".to_string()),
+        ];
+
+        let issues: Vec<(String, String)> = check(&vars, rules)
+            .into_iter()
+            .map(|issue| (issue.key, issue.message))
+            .collect();
+
+        assert_eq!(
+            issues,
+            vec![
+                (
+                    "DATABASE_URL".to_string(),
+                    "required but missing".to_string()
+                ),
+                (
+                    "LOG_LEVEL".to_string(),
+                    "expected one of debug, info".to_string()
+                ),
+                ("PORT".to_string(), "expected an integer".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_profile() {
+        let schema: Schema =
+            toml::from_str("[profiles.staging]\nPORT = { type = \"int\" }\n").unwrap();
+        let mut defined = BTreeMap::new();
+        assert!(schema.verify_profiles(&defined).is_err());
+
+        defined.insert(
+            "staging".to_string(),
+            ProfileConfig {
+                files: vec![".env.staging".to_string()],
+                restore: BTreeMap::new(),
+                passphrase_env: None,
+            },
+        );
+        assert!(schema.verify_profiles(&defined).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_default() {
+        let schema: Schema =
+            toml::from_str("[files.\".env\"]\nPORT = { type = \"int\", default = \"x\" }\n")
+                .unwrap();
+        assert!(schema.verify().is_err());
+    }
+}
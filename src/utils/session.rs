@@ -1,6 +1,5 @@
 use anyhow::bail;
 use argon2::password_hash::rand_core::RngCore;
-use argon2::{Algorithm, Argon2, Params, Version};
 use chacha20poly1305::aead::OsRng;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -11,6 +10,8 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use once_cell::sync::OnceCell;
 
+use crate::commands::crypto::KdfParams;
+
 static SESSION_KEY: OnceCell<[u8; 32]> = OnceCell::new();
 static PASSPHRASE_OVERRIDE: OnceCell<Mutex<Option<String>>> = OnceCell::new();
 
@@ -255,6 +256,10 @@ fn decrypt_manifest_key(
     Ok(plaintext)
 }
 
+/// Derives the project key. Unlike file blobs, it has no header to record its
+/// parameters, so it always uses the legacy ones: honouring `[crypto]` here
+/// would change the key and lock every clone out of the existing history.
+/// `envy rekey` is the way to move a project to a stronger passphrase.
 pub fn derive_manifest_key_from_passphrase(
     passphrase: &str,
     project_id: &str,
@@ -265,12 +270,7 @@ pub fn derive_manifest_key_from_passphrase(
     let hash = hasher.finalize();
     let salt: [u8; 16] = hash[..16].try_into().unwrap();
 
-    let argon2 = Argon2::new(
-        Algorithm::Argon2id,
-        Version::V0x13,
-        Params::new(19456, 2, 1, Some(32))
-            .map_err(|e| anyhow::anyhow!("Failed to create Argon2 params: {}", e))?,
-    );
+    let argon2 = KdfParams::LEGACY.argon2()?;
     let pass = passphrase.as_bytes();
     let mut key = [0u8; 32];
     argon2
@@ -1,5 +1,11 @@
 use std::path::Path;
 
+use super::commit::DEFAULT_BRANCH;
+use super::tag::tag_blob_path;
+use super::ui::short_hash;
+
+const DOWNLOAD_ATTEMPTS: usize = 3;
+
 #[derive(serde::Deserialize)]
 struct SignedUrlResponse {
     method: String,
@@ -36,14 +42,25 @@ struct UpdateHeadRequest {
     expected_head: Option<String>,
 }
 
+/// The server endpoint holding a branch's commit (or another ref, such as the
+/// tag index). The default branch uses the project HEAD, which predates branches.
+fn ref_url(server: &str, project_id: &str, branch: &str) -> String {
+    if branch == DEFAULT_BRANCH {
+        format!("{}/projects/{}/head", server, project_id)
+    } else {
+        format!("{}/projects/{}/refs/{}", server, project_id, branch)
+    }
+}
+
 pub async fn fetch_remote_head(
     client: &reqwest::Client,
     server: &str,
     token: &str,
     project_id: &str,
+    branch: &str,
 ) -> anyhow::Result<Option<String>> {
     let res: HeadResponse = client
-        .get(format!("{}/projects/{}/head", server, project_id))
+        .get(ref_url(server, project_id, branch))
         .bearer_auth(token)
         .send()
         .await?
@@ -59,6 +76,7 @@ pub async fn update_remote_head(
     server: &str,
     token: &str,
     project_id: &str,
+    branch: &str,
     new_head: &str,
     expected_head: Option<&str>,
 ) -> anyhow::Result<()> {
@@ -68,7 +86,7 @@ pub async fn update_remote_head(
     };
 
     let response = client
-        .put(format!("{}/projects/{}/head", server, project_id))
+        .put(ref_url(server, project_id, branch))
         .bearer_auth(token)
         .json(&body)
         .send()
@@ -122,35 +140,64 @@ pub async fn download_commit(
     token: &str,
     project_id: &str,
     commit_hash: &str,
+) -> anyhow::Result<()> {
+    let bytes = download_object(client, server, token, project_id, commit_hash, "commit").await?;
+
+    let path = std::path::Path::new(".envoy/cache/commits").join(format!("{}.blob", commit_hash));
+    tokio::fs::create_dir_all(".envoy/cache/commits").await?;
+    tokio::fs::write(path, &bytes).await?;
+
+    Ok(())
+}
+
+pub async fn upload_tag_object(
+    client: &reqwest::Client,
+    server: &str,
+    token: &str,
+    project_id: &str,
+    hash: &str,
+    tag_path: &Path,
 ) -> anyhow::Result<()> {
     let response = client
-        .get(format!(
-            "{}/projects/{}/blobs/{}/download?type=commit",
-            server, project_id, commit_hash
+        .post(format!(
+            "{}/projects/{}/blobs/{}/upload?type=tag",
+            server, project_id, hash
         ))
         .bearer_auth(token)
         .send()
         .await?;
-    let res = parse_signed_url_response(response, "commit download URL request").await?;
+    let res = parse_signed_url_response(response, "tag upload URL request").await?;
 
-    let bytes = client
-        .get(&res.url)
+    if res.method.to_uppercase() != "PUT" {
+        anyhow::bail!("Expected PUT method, got {}", res.method);
+    }
+
+    let data = tokio::fs::read(tag_path).await?;
+
+    client
+        .put(&res.url)
+        .body(data)
         .send()
         .await?
-        .error_for_status()?
-        .bytes()
-        .await?;
+        .error_for_status()?;
 
-    use sha2::{Digest, Sha256};
-    let mut hasher = Sha256::new();
-    hasher.update(&bytes);
-    let computed = format!("{:x}", hasher.finalize());
-    if computed != *commit_hash {
-        anyhow::bail!("Hash mismatch for commit {}", commit_hash);
-    }
+    Ok(())
+}
 
-    let path = std::path::Path::new(".envoy/cache/commits").join(format!("{}.blob", commit_hash));
-    tokio::fs::create_dir_all(".envoy/cache/commits").await?;
+/// Downloads a tag object or tag index into the local tag cache.
+pub async fn download_tag_object(
+    client: &reqwest::Client,
+    server: &str,
+    token: &str,
+    project_id: &str,
+    hash: &str,
+) -> anyhow::Result<()> {
+    let bytes = download_object(client, server, token, project_id, hash, "tag").await?;
+
+    let path = tag_blob_path(hash);
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
     tokio::fs::write(path, &bytes).await?;
 
     Ok(())
@@ -197,31 +244,7 @@ pub async fn download_blob(
     project_id: &str,
     hash: &str,
 ) -> anyhow::Result<()> {
-    let response = client
-        .get(format!(
-            "{}/projects/{}/blobs/{}/download",
-            server, project_id, hash
-        ))
-        .bearer_auth(token)
-        .send()
-        .await?;
-    let res = parse_signed_url_response(response, "blob download URL request").await?;
-
-    let bytes = client
-        .get(&res.url)
-        .send()
-        .await?
-        .error_for_status()?
-        .bytes()
-        .await?;
-
-    use sha2::{Digest, Sha256};
-    let mut hasher = Sha256::new();
-    hasher.update(&bytes);
-    let computed = format!("{:x}", hasher.finalize());
-    if computed != *hash {
-        anyhow::bail!("Hash mismatch for blob {}", hash);
-    }
+    let bytes = download_object(client, server, token, project_id, hash, "blob").await?;
 
     let path = std::path::Path::new(".envoy/cache").join(format!("{}.blob", hash));
 
@@ -271,40 +294,82 @@ pub async fn download_manifest(
     project_id: &str,
     manifest_hash: &str,
 ) -> anyhow::Result<()> {
-    let response = client
-        .get(format!(
-            "{}/projects/{}/blobs/{}/download?type=manifest",
-            server, project_id, manifest_hash
-        ))
-        .bearer_auth(token)
-        .send()
-        .await?;
-    let res = parse_signed_url_response(response, "manifest download URL request").await?;
+    let bytes =
+        download_object(client, server, token, project_id, manifest_hash, "manifest").await?;
 
-    let bytes = client
-        .get(&res.url)
-        .send()
-        .await?
-        .error_for_status()?
-        .bytes()
-        .await?;
+    let path = std::path::Path::new(".envoy/cache").join(format!("{}.blob", manifest_hash));
+
+    tokio::fs::create_dir_all(".envoy/cache").await?;
+    tokio::fs::write(path, &bytes).await?;
+
+    Ok(())
+}
 
+/// Checks that `bytes` hash to the content address they were requested or stored under.
+pub fn verify_object_hash(bytes: &[u8], expected: &str, description: &str) -> anyhow::Result<()> {
     use sha2::{Digest, Sha256};
-    let mut hasher = Sha256::new();
-    hasher.update(&bytes);
-    let computed = format!("{:x}", hasher.finalize());
-    if computed != *manifest_hash {
+    let computed = hex::encode(Sha256::digest(bytes));
+
+    if computed != expected {
         anyhow::bail!(
-            "Manifest integrity check failed: expected {}, got {}",
-            &manifest_hash[..12],
-            &computed[..12]
+            "{} {} failed integrity check (content hashes to {})",
+            description,
+            short_hash(expected, 12),
+            short_hash(&computed, 12)
         );
     }
 
-    let path = std::path::Path::new(".envoy/cache").join(format!("{}.blob", manifest_hash));
+    Ok(())
+}
 
-    tokio::fs::create_dir_all(".envoy/cache").await?;
-    tokio::fs::write(path, &bytes).await?;
+/// Downloads an object and verifies its hash before returning it, fetching a
+/// fresh signed URL and retrying if the bytes do not match.
+async fn download_object(
+    client: &reqwest::Client,
+    server: &str,
+    token: &str,
+    project_id: &str,
+    hash: &str,
+    object_type: &str,
+) -> anyhow::Result<Vec<u8>> {
+    let query = match object_type {
+        "blob" => String::new(),
+        other => format!("?type={}", other),
+    };
+    let action = format!("{} download URL request", object_type);
+    let description = format!("Downloaded {}", object_type);
+
+    let mut last_error = None;
+
+    for _ in 0..DOWNLOAD_ATTEMPTS {
+        let response = client
+            .get(format!(
+                "{}/projects/{}/blobs/{}/download{}",
+                server, project_id, hash, query
+            ))
+            .bearer_auth(token)
+            .send()
+            .await?;
+        let res = parse_signed_url_response(response, &action).await?;
+
+        let bytes = client
+            .get(&res.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+
+        match verify_object_hash(&bytes, hash, &description) {
+            Ok(()) => return Ok(bytes.to_vec()),
+            Err(e) => last_error = Some(e),
+        }
+    }
 
-    Ok(())
+    let error = last_error.expect("at least one download attempt");
+    anyhow::bail!(
+        "{} after {} attempts. The remote copy may be corrupted or tampered with.",
+        error,
+        DOWNLOAD_ATTEMPTS
+    )
 }
@@ -0,0 +1,318 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::commands::crypto::{
+    ObjectKind, decrypt_bytes_with_key, encrypt_bytes_with_key, object_aad,
+};
+
+use super::manifest::get_project_key;
+use super::project_config::load_project_config;
+use super::session::clear_session;
+use super::storage::verify_object_hash;
+use super::ui::short_hash;
+
+const TAGS_DIR: &str = ".envoy/cache/tags";
+const TAG_REFS_DIR: &str = ".envoy/refs/tags";
+const REMOTE_TAGS_PATH: &str = ".envoy/refs/remotes/origin/tags";
+
+/// Remote ref holding the hash of the tag index. Branch names cannot contain
+/// '/', so it never collides with a branch ref.
+pub const TAG_INDEX_REF: &str = "tags/index";
+
+/// An annotated tag naming a commit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    pub version: u8,
+    pub name: String,
+    pub commit: String,
+    pub message: String,
+    pub tagger: Option<String>,
+    pub timestamp: u64,
+}
+
+/// Every published tag, by name, as stored on the remote.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagIndex {
+    pub version: u8,
+    pub tags: BTreeMap<String, String>,
+}
+
+impl Default for TagIndex {
+    fn default() -> Self {
+        Self {
+            version: 1,
+            tags: BTreeMap::new(),
+        }
+    }
+}
+
+impl Tag {
+    pub fn new(name: String, commit: String, message: String, tagger: Option<String>) -> Self {
+        use std::time::{SystemTime, UNIX_EPOCH};
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        Self {
+            version: 1,
+            name,
+            commit,
+            message,
+            tagger,
+            timestamp,
+        }
+    }
+}
+
+pub fn validate_tag_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 100
+        && !name.starts_with(['.', '-'])
+        && !name.eq_ignore_ascii_case("HEAD")
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '+'));
+
+    if !valid {
+        bail!(
+            "Invalid tag name '{}'. Use letters, digits, '.', '_', '-' and '+'.",
+            name
+        );
+    }
+    Ok(())
+}
+
+pub fn tag_blob_path(hash: &str) -> PathBuf {
+    Path::new(TAGS_DIR).join(format!("{}.blob", hash))
+}
+
+pub fn tag_object_exists(hash: &str) -> bool {
+    tag_blob_path(hash).exists()
+}
+
+/// Tag objects and the tag index share a directory, but are bound to their
+/// role by the associated data.
+fn save_object(plaintext: &[u8], role: &str, manifest_key: &[u8]) -> Result<String> {
+    let project = load_project_config()?;
+    let aad = object_aad(&project.project_id, ObjectKind::Tag, role);
+    let encrypted = encrypt_bytes_with_key(plaintext, manifest_key, &aad)?;
+
+    let hash_hex = hex::encode(Sha256::digest(&encrypted));
+
+    fs::create_dir_all(TAGS_DIR)
+        .map_err(|e| anyhow::anyhow!("Failed to create tags directory: {}", e))?;
+    fs::write(tag_blob_path(&hash_hex), encrypted).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to write tag object {}: {}",
+            short_hash(&hash_hex, 8),
+            e
+        )
+    })?;
+
+    Ok(hash_hex)
+}
+
+fn read_object(hash: &str) -> Result<Vec<u8>> {
+    let path = tag_blob_path(hash);
+    if !path.exists() {
+        bail!(
+            "Tag object {} not found locally. Run `envy pull` to fetch it.",
+            short_hash(hash, 8)
+        );
+    }
+
+    let encrypted = fs::read(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read tag object {}: {}", short_hash(hash, 8), e))?;
+    verify_object_hash(&encrypted, hash, "Cached tag object")?;
+
+    Ok(encrypted)
+}
+
+fn decrypt_object(
+    encrypted: &[u8],
+    hash: &str,
+    role: &str,
+    manifest_key: &[u8],
+    project_id: &str,
+) -> Result<Vec<u8>> {
+    let aad = object_aad(project_id, ObjectKind::Tag, role);
+    decrypt_bytes_with_key(encrypted, manifest_key, &aad).map_err(|_| {
+        anyhow::anyhow!(
+            "Failed to decrypt tag object {}. The passphrase may be incorrect.",
+            short_hash(hash, 8)
+        )
+    })
+}
+
+fn load_object(hash: &str, role: &str, clear_session_on_failure: bool) -> Result<Vec<u8>> {
+    let encrypted = read_object(hash)?;
+    let manifest_key = get_project_key()?;
+    let project = load_project_config()?;
+
+    let result = decrypt_object(&encrypted, hash, role, &manifest_key, &project.project_id);
+    if result.is_err() && clear_session_on_failure {
+        clear_session(&project.project_id)?;
+    }
+    result
+}
+
+fn load_object_with_key(hash: &str, role: &str, manifest_key: &[u8]) -> Result<Vec<u8>> {
+    let encrypted = read_object(hash)?;
+    let project = load_project_config()?;
+    decrypt_object(&encrypted, hash, role, manifest_key, &project.project_id)
+}
+
+pub fn save_tag(tag: &Tag) -> Result<String> {
+    save_tag_with_key(tag, &get_project_key()?)
+}
+
+pub fn save_tag_with_key(tag: &Tag, manifest_key: &[u8]) -> Result<String> {
+    let plaintext =
+        serde_json::to_vec(tag).map_err(|e| anyhow::anyhow!("Failed to serialize tag: {}", e))?;
+    save_object(&plaintext, "", manifest_key)
+}
+
+pub fn load_tag(hash: &str) -> Result<Tag> {
+    read_tag(hash, true)
+}
+
+/// Loads a tag that may legitimately be unreadable, such as a local tag left
+/// behind by a rekey. A failure does not clear the session.
+pub fn try_load_tag(hash: &str) -> Option<Tag> {
+    read_tag(hash, false).ok()
+}
+
+pub fn load_tag_with_key(hash: &str, manifest_key: &[u8]) -> Result<Tag> {
+    parse_tag(&load_object_with_key(hash, "", manifest_key)?, hash)
+}
+
+fn read_tag(hash: &str, clear_session_on_failure: bool) -> Result<Tag> {
+    parse_tag(&load_object(hash, "", clear_session_on_failure)?, hash)
+}
+
+fn parse_tag(plaintext: &[u8], hash: &str) -> Result<Tag> {
+    let tag: Tag = serde_json::from_slice(plaintext)
+        .map_err(|e| anyhow::anyhow!("Failed to parse tag {}: {}", short_hash(hash, 8), e))?;
+
+    if tag.version != 1 {
+        bail!(
+            "Unsupported tag version {}. Please update envy.",
+            tag.version
+        );
+    }
+
+    Ok(tag)
+}
+
+pub fn save_tag_index(index: &TagIndex) -> Result<String> {
+    save_tag_index_with_key(index, &get_project_key()?)
+}
+
+pub fn save_tag_index_with_key(index: &TagIndex, manifest_key: &[u8]) -> Result<String> {
+    let plaintext = serde_json::to_vec(index)
+        .map_err(|e| anyhow::anyhow!("Failed to serialize tag index: {}", e))?;
+    save_object(&plaintext, "index", manifest_key)
+}
+
+pub fn load_tag_index(hash: &str) -> Result<TagIndex> {
+    parse_tag_index(&load_object(hash, "index", true)?, hash)
+}
+
+pub fn load_tag_index_with_key(hash: &str, manifest_key: &[u8]) -> Result<TagIndex> {
+    parse_tag_index(&load_object_with_key(hash, "index", manifest_key)?, hash)
+}
+
+fn parse_tag_index(plaintext: &[u8], hash: &str) -> Result<TagIndex> {
+    let index: TagIndex = serde_json::from_slice(plaintext)
+        .map_err(|e| anyhow::anyhow!("Failed to parse tag index {}: {}", short_hash(hash, 8), e))?;
+
+    if index.version != 1 {
+        bail!(
+            "Unsupported tag index version {}. Please update envy.",
+            index.version
+        );
+    }
+
+    Ok(index)
+}
+
+/// The tag object a local tag name points to.
+pub fn read_tag_ref(name: &str) -> Option<String> {
+    fs::read_to_string(Path::new(TAG_REFS_DIR).join(name))
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+pub fn write_tag_ref(name: &str, tag_hash: &str) -> Result<()> {
+    fs::create_dir_all(TAG_REFS_DIR)?;
+    fs::write(Path::new(TAG_REFS_DIR).join(name), tag_hash)?;
+    Ok(())
+}
+
+/// Local tags with their tag object hashes, sorted by name.
+pub fn list_tags() -> Vec<(String, String)> {
+    let mut tags: Vec<(String, String)> = fs::read_dir(TAG_REFS_DIR)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    read_tag_ref(&name).map(|hash| (name, hash))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    tags.sort();
+    tags
+}
+
+/// The commit a tag names, if a tag called `name` exists locally.
+pub fn resolve_tag(name: &str) -> Result<Option<String>> {
+    if validate_tag_name(name).is_err() {
+        return Ok(None);
+    }
+
+    match read_tag_ref(name) {
+        Some(hash) => Ok(Some(load_tag(&hash)?.commit)),
+        None => Ok(None),
+    }
+}
+
+/// The tag index last fetched from or pushed to the remote.
+pub fn read_remote_tags() -> Option<String> {
+    fs::read_to_string(REMOTE_TAGS_PATH)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+pub fn write_remote_tags(index_hash: &str) -> Result<()> {
+    if let Some(parent) = Path::new(REMOTE_TAGS_PATH).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(REMOTE_TAGS_PATH, index_hash)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_tag_name() {
+        assert!(validate_tag_name("v2.3.0").is_ok());
+        assert!(validate_tag_name("release-2024_01+build.5").is_ok());
+        assert!(validate_tag_name("").is_err());
+        assert!(validate_tag_name("-v1").is_err());
+        assert!(validate_tag_name("v1/hotfix").is_err());
+        assert!(validate_tag_name("HEAD").is_err());
+    }
+}
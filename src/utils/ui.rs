@@ -10,6 +10,12 @@ pub const ICON_ARROW: &str = "→";
 pub const ICON_WARN: &str = "!";
 pub const ICON_BULLET: &str = "•";
 
+/// The first `len` characters of a hash for display, or the whole value if it
+/// is shorter, since hashes may come from a manifest or an argument.
+pub fn short_hash(hash: &str, len: usize) -> &str {
+    hash.get(..len).unwrap_or(hash)
+}
+
 pub fn is_interactive() -> bool {
     io::stdin().is_terminal()
 }
@@ -254,3 +260,15 @@ pub fn prompt_file_passphrase(file_path: &str) -> anyhow::Result<PassphraseResul
         Ok(PassphraseResult::Passphrase(result))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_hash() {
+        assert_eq!(short_hash("0123456789abcdef", 8), "01234567");
+        assert_eq!(short_hash("abc", 12), "abc");
+        assert_eq!(short_hash("", 8), "");
+    }
+}
@@ -0,0 +1,350 @@
+use std::fs;
+use std::path::Path;
+
+use std::collections::BTreeSet;
+
+use anyhow::{Result, bail};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use walkdir::WalkDir;
+
+use crate::commands::crypto::plaintext_digest;
+
+use super::manifest::{FileEntry, LazyProjectKey, Manifest};
+use super::paths::{normalize_path, to_native_path};
+use super::profile::{Profile, restore_targets};
+use super::project_config::ProjectConfig;
+
+pub const ENVOYIGNORE_PATH: &str = ".envoyignore";
+
+/// Directories never scanned for untracked env files.
+const SKIPPED_DIRS: &[&str] = &[".git", ".envoy", "node_modules", "target"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileStatus {
+    /// On disk and in the staged manifest match HEAD.
+    Unchanged,
+    /// The file on disk differs from what was encrypted.
+    Modified,
+    /// Encrypted but not yet committed.
+    Staged,
+    /// Tracked in HEAD but removed from the staged manifest.
+    Removed,
+    /// Encrypted here but since removed from disk.
+    Deleted,
+    /// Committed but missing from disk, e.g. skipped during pull.
+    NotRestored,
+    /// Tracked before digests were recorded, so it cannot be compared.
+    Unknown,
+}
+
+impl FileStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FileStatus::Unchanged => "unchanged",
+            FileStatus::Modified => "modified",
+            FileStatus::Staged => "staged",
+            FileStatus::Removed => "removed",
+            FileStatus::Deleted => "deleted",
+            FileStatus::NotRestored => "not restored",
+            FileStatus::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskState {
+    Missing,
+    /// The entry has no digest to compare against.
+    NoDigest,
+    Matches,
+    Differs,
+}
+
+/// Compares a tracked entry with the plaintext file at `path` using its keyed
+/// digest. The project key is only fetched when the sizes match.
+pub fn compare_with_disk(
+    path: &str,
+    entry: &FileEntry,
+    manifest_key: &mut LazyProjectKey,
+) -> Result<DiskState> {
+    let target = to_native_path(path);
+    if !target.is_file() {
+        return Ok(DiskState::Missing);
+    }
+
+    let Some(digest) = &entry.digest else {
+        return Ok(DiskState::NoDigest);
+    };
+
+    let plaintext = fs::read(&target)
+        .map_err(|e| anyhow::anyhow!("Failed to read '{}': {}", target.display(), e))?;
+
+    if entry
+        .size
+        .is_some_and(|size| size != plaintext.len() as u64)
+        || plaintext_digest(manifest_key.get()?, &plaintext)? != *digest
+    {
+        return Ok(DiskState::Differs);
+    }
+
+    Ok(DiskState::Matches)
+}
+
+/// Status of every path in the staged or HEAD manifest, sorted by path. With a
+/// profile, only its files are listed and each is compared at its restore target.
+pub fn tracked_statuses(
+    manifest: &Manifest,
+    head: Option<&Manifest>,
+    manifest_key: &mut LazyProjectKey,
+    profile: Option<&Profile>,
+) -> Result<Vec<(String, FileStatus)>> {
+    let included = |path: &str| profile.is_none_or(|profile| profile.includes(path));
+    let mut statuses = Vec::new();
+
+    for (path, entry) in &manifest.files {
+        if !included(path) {
+            continue;
+        }
+
+        let staged = head.and_then(|head| head.files.get(path)) != Some(entry);
+        let target = match profile {
+            Some(profile) => profile.target(path),
+            None => path.clone(),
+        };
+
+        let status = match compare_with_disk(&target, entry, manifest_key)? {
+            DiskState::Missing if staged => FileStatus::Deleted,
+            DiskState::Missing => FileStatus::NotRestored,
+            DiskState::Differs => FileStatus::Modified,
+            _ if staged => FileStatus::Staged,
+            DiskState::NoDigest => FileStatus::Unknown,
+            DiskState::Matches => FileStatus::Unchanged,
+        };
+
+        statuses.push((path.clone(), status));
+    }
+
+    if let Some(head) = head {
+        for path in head.files.keys() {
+            if !manifest.files.contains_key(path) && included(path) {
+                statuses.push((path.clone(), FileStatus::Removed));
+            }
+        }
+    }
+
+    statuses.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(statuses)
+}
+
+pub fn build_globset(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| anyhow::anyhow!("Invalid pattern '{}': {}", pattern, e))?;
+        builder.add(glob);
+    }
+
+    builder
+        .build()
+        .map_err(|e| anyhow::anyhow!("Invalid patterns: {}", e))
+}
+
+/// Whether a normalized path matches. Patterns without a `/` match the file
+/// name anywhere in the tree, like `.gitignore` entries.
+pub fn matches_path(set: &GlobSet, path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    set.is_match(path) || set.is_match(name)
+}
+
+/// The project's `.gitignore`, used to skip ignored directories such as build
+/// output. Ignored files are still visited since env files are usually ignored.
+fn project_gitignore() -> Gitignore {
+    let mut builder = GitignoreBuilder::new(".");
+    if Path::new(".gitignore").is_file() {
+        let _ = builder.add(".gitignore");
+    }
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+/// Normalized paths of files under `root`, relative to it, skipping
+/// dependency, VCS and gitignored directories.
+pub fn walk_files(root: &Path) -> Vec<String> {
+    let gitignore = project_gitignore();
+
+    WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !entry.file_type().is_dir()
+                || !(SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref())
+                    || gitignore.matched(entry.path(), true).is_ignore())
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(root).ok()?;
+            Some(normalize_path(&relative.to_string_lossy()))
+        })
+        .collect()
+}
+
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '[', '{'])
+}
+
+/// Expands `envy encrypt` arguments into project-relative paths: literal paths
+/// are kept, glob patterns are matched against files in the project and
+/// directories are walked recursively.
+pub fn expand_targets(patterns: &[String], dirs: &[String]) -> Result<Vec<String>> {
+    let mut targets = BTreeSet::new();
+    let mut project_files: Option<Vec<String>> = None;
+
+    for pattern in patterns {
+        let normalized = normalize_path(pattern);
+
+        if !is_glob(&normalized) {
+            if !to_native_path(&normalized).is_file() {
+                bail!("'{}' is not a file", pattern);
+            }
+            targets.insert(normalized);
+            continue;
+        }
+
+        let set = build_globset(std::slice::from_ref(&normalized))?;
+        let files = project_files.get_or_insert_with(|| walk_files(Path::new(".")));
+        let before = targets.len();
+        targets.extend(
+            files
+                .iter()
+                .filter(|path| set.is_match(path.as_str()))
+                .cloned(),
+        );
+
+        if targets.len() == before {
+            bail!("No files match '{}'", pattern);
+        }
+    }
+
+    for dir in dirs {
+        let prefix = normalize_path(dir);
+        let root = if prefix.is_empty() {
+            Path::new(".").to_path_buf()
+        } else {
+            to_native_path(&prefix)
+        };
+        if !root.is_dir() {
+            bail!("'{}' is not a directory", dir);
+        }
+
+        let files = walk_files(&root);
+        if files.is_empty() {
+            bail!("No files found under '{}'", dir);
+        }
+        targets.extend(files.into_iter().map(|path| {
+            if prefix.is_empty() {
+                path
+            } else {
+                format!("{}/{}", prefix, path)
+            }
+        }));
+    }
+
+    Ok(targets.into_iter().collect())
+}
+
+/// Which files should be tracked: `[tracking] include` patterns, minus
+/// `[tracking] exclude` patterns and `.envoyignore` entries.
+pub struct TrackingRules {
+    include: GlobSet,
+    exclude: GlobSet,
+    envoyignore: Gitignore,
+    /// Paths profiles restore tracked files into, which are never suggested.
+    restore_targets: Vec<String>,
+}
+
+impl TrackingRules {
+    pub fn load(project: &ProjectConfig) -> Result<Self> {
+        let mut builder = GitignoreBuilder::new(".");
+        if Path::new(ENVOYIGNORE_PATH).is_file()
+            && let Some(e) = builder.add(ENVOYIGNORE_PATH)
+        {
+            bail!("Failed to read {}: {}", ENVOYIGNORE_PATH, e);
+        }
+        let envoyignore = builder
+            .build()
+            .map_err(|e| anyhow::anyhow!("Invalid {}: {}", ENVOYIGNORE_PATH, e))?;
+
+        Ok(Self {
+            include: build_globset(&project.tracking.include)?,
+            exclude: build_globset(&project.tracking.exclude)?,
+            envoyignore,
+            restore_targets: restore_targets(project),
+        })
+    }
+
+    pub fn excludes(&self, path: &str) -> bool {
+        matches_path(&self.exclude, path)
+            || self
+                .envoyignore
+                .matched_path_or_any_parents(path, false)
+                .is_ignore()
+    }
+
+    pub fn wants(&self, path: &str) -> bool {
+        path != ENVOYIGNORE_PATH
+            && matches_path(&self.include, path)
+            && !self.excludes(path)
+            && !self.restore_targets.iter().any(|target| target == path)
+    }
+}
+
+/// Files under `root` the tracking rules want that the manifest does not track.
+/// `root` is relative to the project root.
+pub fn untracked_files(root: &Path, manifest: &Manifest, rules: &TrackingRules) -> Vec<String> {
+    let prefix = normalize_path(&root.to_string_lossy());
+
+    let mut untracked: Vec<String> = walk_files(root)
+        .into_iter()
+        .map(|path| {
+            if prefix.is_empty() {
+                path
+            } else {
+                format!("{}/{}", prefix, path)
+            }
+        })
+        .filter(|path| rules.wants(path) && !manifest.files.contains_key(path))
+        .collect();
+
+    untracked.sort();
+    untracked
+}
+
+/// Tracked paths that the tracking rules now exclude.
+pub fn excluded_tracked(manifest: &Manifest, rules: &TrackingRules) -> Vec<String> {
+    manifest
+        .files
+        .keys()
+        .filter(|path| rules.excludes(path))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_path() {
+        let set = build_globset(&[".env*".to_string(), "config/*.secrets".to_string()]).unwrap();
+
+        assert!(matches_path(&set, ".env"));
+        assert!(matches_path(&set, "api/.env.local"));
+        assert!(matches_path(&set, "config/app.secrets"));
+        assert!(!matches_path(&set, "nested/config/app.secrets"));
+        assert!(!matches_path(&set, "README.md"));
+    }
+}